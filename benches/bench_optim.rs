@@ -15,7 +15,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         Unit::new(UnitType::Rider),
         Unit::new(UnitType::Rider),
         Unit::new(UnitType::Rider),
-        Unit::new(UnitType::Rider).with_current_hp(6.0),
+        Unit::new(UnitType::Rider).with_current_hp(6),
         Unit::new(UnitType::Archer),
     ]);
     let defenders = Units::from([
@@ -41,7 +41,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         Unit::new(UnitType::Rider),
         Unit::new(UnitType::Rider),
         Unit::new(UnitType::Rider),
-        Unit::new(UnitType::Rider).with_current_hp(6.0),
+        Unit::new(UnitType::Rider).with_current_hp(6),
     ]);
     let defenders = Units::from([Unit::new(UnitType::Giant)]);
 
@@ -5,17 +5,47 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 struct RawUnitData {
     attack: f32,
-    #[allow(
-        unused,
-        reason = "cost is not currently used, but is in the unit_data file"
-    )]
+    class: String,
     cost: u8,
     defense: f32,
-    hp: f32,
+    hp: u16,
+    movement: u8,
     range: u8,
     retaliates: bool,
+    skills: Vec<String>,
     surprise: bool,
-    trait_effects: Option<String>,
+    tribe: String,
+    /// Whether the unit moves on water rather than on land.
+    water: bool,
+}
+
+/// Stars it takes to upgrade a unit into a bomber, raft, rammer and scout.
+const NAVAL_UPGRADE_COSTS: [u32; 4] = [15, 0, 5, 5];
+
+/// Converts a stat to the fixed-point tenths used by `Unit`.
+fn tenths(stat: f32) -> u8 {
+    (stat * 10.0).round() as u8
+}
+
+/// Combines bitflags constants into a const expression, or returns `empty()` if there are
+/// none. `|` is not const, so they are combined with `union`.
+fn flags_expr(ty: &str, names: &[String]) -> String {
+    if names.is_empty() {
+        format!("{ty}::empty()")
+    } else {
+        names
+            .iter()
+            .map(|name| format!("{ty}::{}", name.to_uppercase()))
+            .reduce(|all, flag| format!("{all}.union({flag})"))
+            .unwrap_or_default()
+    }
+}
+
+/// A `UnitMetadata` literal.
+fn metadata_expr(tribe: &str, cost: u32, class: &str, water: bool) -> String {
+    format!(
+        "UnitMetadata {{ tribe: Tribe::{tribe}, cost: {cost}, class: UnitClass::{class}, water: {water} }}"
+    )
 }
 
 fn main() {
@@ -24,28 +54,46 @@ fn main() {
 
     let mut out = String::new();
     out.push_str("// AUTO-GENERATED FILE, DO NOT EDIT\n");
-    out.push_str("use crate::unit::{StatusEffects, Unit, UnitType};\n\n");
+    out.push_str(
+        "use crate::unit::{Skills, StatusEffects, Tribe, Unit, UnitClass, UnitMetadata, UnitType};\n\n",
+    );
     out.push_str("pub const UNIT_TYPE_DATA: &[Unit] = &[\n");
 
+    // One entry per row of `UNIT_TYPE_DATA`, naval variants included.
+    let mut metadata = Vec::new();
+
     for (name, data) in raw_unit_data {
-        if let Some(trait_effects) = data.trait_effects {
-            out.push_str(&format!("    Unit {{ unit_type: UnitType::{}, attack: {:?}, current_hp: {:?}, defense: {:?}, max_hp: {:?}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, trait_effects: StatusEffects::{}, defense_bonus: 1.0 }},\n", name, data.attack, data.hp, data.defense, data.hp, data.range, data.retaliates, data.surprise, trait_effects));
-        } else {
-            out.push_str(&format!("    Unit {{ unit_type: UnitType::{}, attack: {:?}, current_hp: {:?}, defense: {:?}, max_hp: {:?}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, trait_effects: StatusEffects::empty(), defense_bonus: 1.0 }},\n", name, data.attack, data.hp, data.defense, data.hp, data.range, data.retaliates, data.surprise));
-        }
+        metadata.push(metadata_expr(
+            &data.tribe,
+            u32::from(data.cost),
+            &data.class,
+            data.water,
+        ));
+        let skills = flags_expr("Skills", &data.skills);
+        out.push_str(&format!("    Unit {{ unit_type: UnitType::{}, attack: {}, current_hp: {}, defense: {}, max_hp: {}, movement: {}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, skills: {}, defense_bonus: 10, terrain_bonus: false, position: None, kills: 0, body: None, age: 0 }},\n", name, tenths(data.attack), data.hp, tenths(data.defense), data.hp, data.movement, data.range, data.retaliates, data.surprise, skills));
         match name.as_str() {
-            "BabyDragon" | "Cloak" | "Crab" | "Dagger" | "FireDragon" | "Giant" | "Jelly"
-            | "Juggernaut" | "Phychi" | "Pirate" | "Puffer" | "Raychi" | "Segment" | "Shark"
-            | "Tridention" => {}
+            "BabyDragon" | "Cloak" | "Crab" | "Dagger" | "Egg" | "FireDragon" | "Giant"
+            | "Jelly" | "Juggernaut" | "Phychi" | "Pirate" | "Puffer" | "Raychi" | "Segment"
+            | "Shark" | "Tridention" => {}
             _ => {
-                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Bomber, attack: {:?}, current_hp: {:?}, defense: {:?}, max_hp: {:?}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, trait_effects: StatusEffects::empty(), defense_bonus: 1.0 }},\n", name, 3.0, data.hp, 2.0, data.hp, 3, false, false));
-                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Raft, attack: {:?}, current_hp: {:?}, defense: {:?}, max_hp: {:?}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, trait_effects: StatusEffects::empty(), defense_bonus: 1.0 }},\n", name, 0.0, data.hp, 2.0, data.hp, 0, false, false));
-                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Rammer, attack: {:?}, current_hp: {:?}, defense: {:?}, max_hp: {:?}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, trait_effects: StatusEffects::empty(), defense_bonus: 1.0 }},\n", name, 3.0, data.hp, 3.0, data.hp, 1, true, false));
-                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Scout, attack: {:?}, current_hp: {:?}, defense: {:?}, max_hp: {:?}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, trait_effects: StatusEffects::empty(), defense_bonus: 1.0 }},\n", name, 2.0, data.hp, 1.0, data.hp, 2, true, false));
+                // Boats cost the unit they carry plus the upgrade, in `NAVAL_NAMES` order.
+                metadata.extend(NAVAL_UPGRADE_COSTS.map(|upgrade| {
+                    metadata_expr(&data.tribe, u32::from(data.cost) + upgrade, "Naval", true)
+                }));
+                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Bomber, attack: {}, current_hp: {}, defense: {}, max_hp: {}, movement: {}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, skills: Skills::SPLASH, defense_bonus: 10, terrain_bonus: false, position: None, kills: 0, body: None, age: 0 }},\n", name, 30, data.hp, 20, data.hp, 2, 3, false, false));
+                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Raft, attack: {}, current_hp: {}, defense: {}, max_hp: {}, movement: {}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, skills: Skills::empty(), defense_bonus: 10, terrain_bonus: false, position: None, kills: 0, body: None, age: 0 }},\n", name, 0, data.hp, 20, data.hp, 2, 0, false, false));
+                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Rammer, attack: {}, current_hp: {}, defense: {}, max_hp: {}, movement: {}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, skills: Skills::empty(), defense_bonus: 10, terrain_bonus: false, position: None, kills: 0, body: None, age: 0 }},\n", name, 30, data.hp, 30, data.hp, 3, 1, true, false));
+                out.push_str(&format!("    Unit {{ unit_type: UnitType::{}Scout, attack: {}, current_hp: {}, defense: {}, max_hp: {}, movement: {}, range: {}, retaliates: {}, status_effects: StatusEffects::empty(), surprise: {}, skills: Skills::empty(), defense_bonus: 10, terrain_bonus: false, position: None, kills: 0, body: None, age: 0 }},\n", name, 20, data.hp, 10, data.hp, 3, 2, true, false));
             }
         }
     }
 
+    out.push_str("];\n\n");
+
+    out.push_str("pub const UNIT_TYPE_METADATA: &[UnitMetadata] = &[\n");
+    for entry in metadata {
+        out.push_str(&format!("    {entry},\n"));
+    }
     out.push_str("];\n");
 
     std::fs::write("./src/unit/generated.rs", out).expect("Unable to write to generated.rs");
@@ -0,0 +1,157 @@
+use crate::{
+    combat::{self, BulkReport, EliminateError, UnitResult},
+    unit::{Hp, Unit},
+};
+
+/// The type of tile a defender stands on.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub enum Terrain {
+    #[default]
+    Field,
+    Forest,
+    Mountain,
+    Water,
+    Ocean,
+}
+
+bitflags::bitflags! {
+    /// Technologies researched by a tribe that affect combat.
+    #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Default)]
+    pub struct Techs: u8 {
+        /// Units in forests get a defense bonus.
+        const ARCHERY = 0b00000001;
+        /// Units on mountains get a defense bonus.
+        const CLIMBING = 0b00000010;
+        /// Units in water and ocean get a defense bonus.
+        const AQUATISM = 0b00000100;
+    }
+}
+
+impl Terrain {
+    /// The technology that grants a defense bonus on this terrain, if any.
+    pub fn defense_tech(self) -> Option<Techs> {
+        match self {
+            Terrain::Field => None,
+            Terrain::Forest => Some(Techs::ARCHERY),
+            Terrain::Mountain => Some(Techs::CLIMBING),
+            Terrain::Water | Terrain::Ocean => Some(Techs::AQUATISM),
+        }
+    }
+}
+
+/// Where a fight takes place: the defender's terrain and the techs of the defender's tribe.
+///
+/// The free functions in [`combat`] assume an open field. The methods here apply the terrain
+/// bonus to the defender first and then defer to them.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub struct Battlefield {
+    pub terrain: Terrain,
+    pub techs: Techs,
+}
+
+impl Battlefield {
+    pub fn new(terrain: Terrain, techs: Techs) -> Self {
+        Self { terrain, techs }
+    }
+
+    /// Whether a defender on this battlefield gets the terrain defense bonus.
+    pub fn has_defense_bonus(&self) -> bool {
+        self.terrain
+            .defense_tech()
+            .is_some_and(|tech| self.techs.contains(tech))
+    }
+
+    /// Returns the unit with its defense bonus updated for this battlefield.
+    pub fn place(&self, unit: Unit) -> Unit {
+        unit.with_terrain_bonus(self.has_defense_bonus())
+    }
+
+    pub fn single_combat(&self, attacker: &Unit, defender: &Unit) -> (UnitResult, UnitResult) {
+        combat::single_combat(attacker, &self.place(defender.clone()))
+    }
+
+    pub fn bulk(&self, attacker: &Unit, defender: Unit) -> u32 {
+        combat::bulk(attacker, self.place(defender))
+    }
+
+    pub fn bulk_copies(
+        &self,
+        attacker: &Unit,
+        defender: Unit,
+    ) -> Result<BulkReport, EliminateError> {
+        combat::bulk_copies(attacker, self.place(defender))
+    }
+
+    pub fn bulk_repeated(
+        &self,
+        attacker: Unit,
+        defender: Unit,
+    ) -> Result<BulkReport, EliminateError> {
+        combat::bulk_repeated(attacker, self.place(defender))
+    }
+
+    pub fn eliminate_defender(
+        &self,
+        attacker: &Unit,
+        defender: Unit,
+    ) -> Result<Hp, EliminateError> {
+        combat::eliminate_defender(attacker, self.place(defender))
+    }
+
+    pub fn eliminate_attacker(
+        &self,
+        attacker: Unit,
+        defender: &Unit,
+    ) -> Result<Hp, EliminateError> {
+        combat::eliminate_attacker(attacker, &self.place(defender.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::{StatusEffects, UnitType};
+
+    use super::*;
+
+    #[test]
+    fn test_wa_wa_forest() {
+        let attacker = Unit::new(UnitType::Warrior);
+        let defender = Unit::new(UnitType::Warrior);
+
+        let without_tech = Battlefield::new(Terrain::Forest, Techs::CLIMBING);
+        let (_, defender_result) = without_tech.single_combat(&attacker, &defender);
+        assert_eq!(defender_result.damage, 5);
+
+        let with_tech = Battlefield::new(Terrain::Forest, Techs::ARCHERY);
+        let (attacker_result, defender_result) = with_tech.single_combat(&attacker, &defender);
+        assert_eq!(attacker_result.damage, 5);
+        assert_eq!(defender_result.damage, 4);
+    }
+
+    #[test]
+    fn test_terrain_bonus_does_not_stack() {
+        let battlefield = Battlefield::new(Terrain::Mountain, Techs::CLIMBING);
+
+        let fortified = battlefield
+            .place(Unit::new(UnitType::Defender).with_status_effects(StatusEffects::FORTIFIED));
+        assert_eq!(fortified.defense_bonus, 15);
+
+        let walled = battlefield
+            .place(Unit::new(UnitType::Defender).with_status_effects(StatusEffects::WALLED));
+        assert_eq!(walled.defense_bonus, 40);
+
+        let poisoned = battlefield
+            .place(Unit::new(UnitType::Defender))
+            .with_status_effects(StatusEffects::POISONED);
+        assert_eq!(poisoned.defense_bonus, 7);
+    }
+
+    #[test]
+    fn test_b_ri_gi_forest() {
+        let attacker = Unit::new(UnitType::Rider);
+        let defender = Unit::new(UnitType::Giant);
+        let battlefield = Battlefield::new(Terrain::Forest, Techs::ARCHERY);
+
+        assert!(battlefield.bulk(&attacker, defender.clone()) > combat::bulk(&attacker, defender));
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+
+use crate::{
+    battlefield::{Battlefield, Techs, Terrain},
+    combat::{self, CombatLog},
+    unit::{Unit, Units},
+};
+
+/// A tile on the square map. The game measures distances in king moves, so every tile has
+/// eight neighbours.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
+pub struct Position {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Position {
+    pub fn new(x: u8, y: u8) -> Self {
+        Self { x, y }
+    }
+
+    /// The number of king moves between the two tiles.
+    pub fn distance(self, other: Position) -> u16 {
+        u16::from(self.x.abs_diff(other.x).max(self.y.abs_diff(other.y)))
+    }
+
+    pub fn is_adjacent(self, other: Position) -> bool {
+        self.distance(other) == 1
+    }
+
+    /// Iterates over the (up to eight) tiles next to this one that have valid coordinates.
+    pub fn neighbours(self) -> impl Iterator<Item = Position> {
+        (-1i16..=1)
+            .flat_map(|dy| (-1i16..=1).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| dx != 0 || dy != 0)
+            .filter_map(move |(dx, dy)| {
+                let x = u8::try_from(i16::from(self.x) + dx).ok()?;
+                let y = u8::try_from(i16::from(self.y) + dy).ok()?;
+                Some(Position::new(x, y))
+            })
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// Why an attack cannot be made on a [`Board`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttackError {
+    /// One of the units has not been placed on the board.
+    NoPosition,
+    /// The unit stands outside the board.
+    OffBoard(Position),
+    /// The target is further away than the attacker's range.
+    OutOfRange { distance: u16, range: u8 },
+    /// None of the attacking side's units can see the target.
+    NotVisible(Position),
+}
+
+impl fmt::Display for AttackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoPosition => write!(f, "unit has no position on the board"),
+            Self::OffBoard(position) => write!(f, "{position} is outside the board"),
+            Self::OutOfRange { distance, range } => {
+                write!(f, "target is {distance} tiles away but range is {range}")
+            }
+            Self::NotVisible(position) => write!(f, "nothing can see the target at {position}"),
+        }
+    }
+}
+
+impl std::error::Error for AttackError {}
+
+/// A rectangular map of terrain that units can be placed on.
+///
+/// Units see one tile around them, or two from a mountain, and can only attack what their
+/// side can see.
+#[derive(Debug, Clone)]
+pub struct Board {
+    width: u8,
+    height: u8,
+    tiles: Vec<Terrain>,
+    /// Techs of the defending tribe, used for terrain defense bonuses.
+    pub defender_techs: Techs,
+}
+
+impl Board {
+    /// Creates a board of open fields.
+    pub fn new(width: u8, height: u8) -> Self {
+        Self {
+            width,
+            height,
+            tiles: vec![Terrain::Field; usize::from(width) * usize::from(height)],
+            defender_techs: Techs::empty(),
+        }
+    }
+
+    pub fn with_terrain(mut self, position: Position, terrain: Terrain) -> Self {
+        self.set_terrain(position, terrain);
+        self
+    }
+
+    pub fn with_defender_techs(mut self, techs: Techs) -> Self {
+        self.defender_techs = techs;
+        self
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        position.x < self.width && position.y < self.height
+    }
+
+    fn tile_index(&self, position: Position) -> Option<usize> {
+        self.contains(position)
+            .then(|| usize::from(position.y) * usize::from(self.width) + usize::from(position.x))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `position` is outside the board.
+    pub fn set_terrain(&mut self, position: Position, terrain: Terrain) {
+        let index = self
+            .tile_index(position)
+            .expect("position should be on the board");
+        self.tiles[index] = terrain;
+    }
+
+    pub fn terrain(&self, position: Position) -> Option<Terrain> {
+        self.tile_index(position).map(|index| self.tiles[index])
+    }
+
+    /// The battlefield a defender standing at `position` fights on.
+    pub fn battlefield(&self, position: Position) -> Battlefield {
+        Battlefield::new(
+            self.terrain(position).unwrap_or_default(),
+            self.defender_techs,
+        )
+    }
+
+    /// How many tiles a unit standing at `position` can see.
+    pub fn vision(&self, position: Position) -> u16 {
+        if self.terrain(position) == Some(Terrain::Mountain) {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Whether any of `viewers` can see `target`.
+    pub fn is_visible(&self, target: Position, viewers: &Units) -> bool {
+        viewers.alive().any(|viewer| {
+            viewer
+                .position
+                .is_some_and(|p| p.distance(target) <= self.vision(p))
+        })
+    }
+
+    /// Checks that `attacker` can attack `defender`, with `attackers` providing vision.
+    pub fn can_attack(
+        &self,
+        attacker: &Unit,
+        defender: &Unit,
+        attackers: &Units,
+    ) -> Result<(), AttackError> {
+        let (Some(from), Some(to)) = (attacker.position, defender.position) else {
+            return Err(AttackError::NoPosition);
+        };
+
+        for position in [from, to] {
+            if !self.contains(position) {
+                return Err(AttackError::OffBoard(position));
+            }
+        }
+
+        if !combat::in_range(attacker, defender) {
+            return Err(AttackError::OutOfRange {
+                distance: from.distance(to),
+                range: attacker.range,
+            });
+        }
+
+        if !self.is_visible(to, attackers) {
+            return Err(AttackError::NotVisible(to));
+        }
+
+        Ok(())
+    }
+
+    /// Applies the terrain defense bonus of each defender's tile.
+    pub fn place_defenders(&self, defenders: Units) -> Units {
+        defenders
+            .into_iter()
+            .map(|defender| match defender.position {
+                Some(position) => self.battlefield(position).place(defender),
+                None => defender,
+            })
+            .collect()
+    }
+
+    /// Checks that every attack in `log` can be made on this board.
+    pub fn validate(&self, attackers: &Units, log: &CombatLog) -> Result<(), AttackError> {
+        log.iter()
+            .try_for_each(|event| self.can_attack(&event.attacker, &event.defender, attackers))
+    }
+
+    /// Finds the best attack order like [`combat::optimized`], only considering attacks that
+    /// can be made on this board.
+    ///
+    /// Defenders get their terrain bonus, and defenders nothing can see are left out.
+    pub fn optimized(&self, attackers: Units, defenders: Units) -> (i32, CombatLog) {
+        let defenders: Units = self
+            .place_defenders(defenders)
+            .into_iter()
+            .filter(|defender| {
+                defender
+                    .position
+                    .is_none_or(|position| self.is_visible(position, &attackers))
+            })
+            .collect();
+
+        combat::optimized(attackers, defenders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::UnitType;
+
+    use super::*;
+
+    #[test]
+    fn test_neighbours() {
+        assert_eq!(Position::new(0, 0).neighbours().count(), 3);
+        assert_eq!(Position::new(4, 4).neighbours().count(), 8);
+        assert!(
+            Position::new(4, 4)
+                .neighbours()
+                .all(|p| p.is_adjacent(Position::new(4, 4)))
+        );
+    }
+
+    #[test]
+    fn test_can_attack() {
+        let board = Board::new(8, 8).with_terrain(Position::new(0, 0), Terrain::Mountain);
+        let archer = Unit::new(UnitType::Archer).with_position(Position::new(0, 0));
+        let warrior = Unit::new(UnitType::Warrior).with_position(Position::new(5, 5));
+        let attackers = Units::from([archer.clone(), warrior]);
+
+        let near = Unit::new(UnitType::Giant).with_position(Position::new(2, 2));
+        assert_eq!(board.can_attack(&archer, &near, &attackers), Ok(()));
+
+        let far = Unit::new(UnitType::Giant).with_position(Position::new(3, 0));
+        assert_eq!(
+            board.can_attack(&archer, &far, &attackers),
+            Err(AttackError::OutOfRange {
+                distance: 3,
+                range: 2
+            })
+        );
+
+        let flat = Board::new(8, 8);
+        assert_eq!(
+            flat.can_attack(&archer, &near, &attackers),
+            Err(AttackError::NotVisible(Position::new(2, 2)))
+        );
+    }
+
+    #[test]
+    fn test_optimized_skips_out_of_range() {
+        let board = Board::new(8, 8);
+        let attackers = Units::from([
+            Unit::new(UnitType::Warrior).with_position(Position::new(0, 0)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(6, 6)),
+        ]);
+        let defenders = Units::from([Unit::new(UnitType::Warrior)
+            .with_current_hp(5)
+            .with_position(Position::new(1, 1))]);
+
+        let (_, log) = board.optimized(attackers.clone(), defenders);
+
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].attacker.position, Some(Position::new(0, 0)));
+        assert_eq!(board.validate(&attackers, &log), Ok(()));
+    }
+}
@@ -1,115 +1,333 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use arrayvec::ArrayVec;
+
 use crate::{
-    unit::{StatusEffects, Unit, UnitType, Units},
+    board::Position,
+    unit::{Hp, KILLS_TO_PROMOTE, MAX_UNITS, STAT_SCALE, Skills, StatusEffects, Unit, Units},
     utils::Perms,
 };
 
-const ELIPSON: f32 = 1e-6;
+/// Attack and defense forces for a fight, scaled by `100 * attacker_max_hp * defender_max_hp`
+/// so that the ratio between them is exact.
+#[inline]
+fn forces(
+    attack: u8,
+    defense: u8,
+    attacker_hp: Hp,
+    attacker_max_hp: Hp,
+    defender_hp: Hp,
+    defender_max_hp: Hp,
+    defense_bonus: u8,
+) -> (u64, u64) {
+    let attack_force =
+        u64::from(attack) * u64::from(attacker_hp) * u64::from(defender_max_hp) * STAT_SCALE;
+    let defense_force = u64::from(defense)
+        * u64::from(defender_hp)
+        * u64::from(defense_bonus)
+        * u64::from(attacker_max_hp);
+
+    (attack_force, defense_force)
+}
+
+/// Computes `force / total_force * stat * 4.5`, rounded half up like the game.
+#[inline]
+fn scaled_damage(force: u64, total_force: u64, stat: u8) -> Hp {
+    // stat * 4.5 == stat * 45 / STAT_SCALE^2 since stat is stored in tenths.
+    let numerator = force * u64::from(stat) * 45;
+    let denominator = total_force * STAT_SCALE * STAT_SCALE;
+
+    ((2 * numerator + denominator) / (2 * denominator)) as Hp
+}
 
+#[allow(
+    clippy::too_many_arguments,
+    reason = "hp values are passed separately to keep the ratio math exact"
+)]
 fn calculate_damage(
-    attack: f32,
-    defense: f32,
-    attacker_health_ratio: f32,
-    defender_health_ratio: f32,
-    defense_bonus: f32,
+    attack: u8,
+    defense: u8,
+    attacker_hp: Hp,
+    attacker_max_hp: Hp,
+    defender_hp: Hp,
+    defender_max_hp: Hp,
+    defense_bonus: u8,
     halved: bool,
-) -> (f32, f32) {
-    let attack_force = attack * attacker_health_ratio;
-    let defense_force = defense * defender_health_ratio * defense_bonus;
-    let total_damage = attack_force + defense_force;
+) -> (Hp, Hp) {
+    let (attack_force, defense_force) = forces(
+        attack,
+        defense,
+        attacker_hp,
+        attacker_max_hp,
+        defender_hp,
+        defender_max_hp,
+        defense_bonus,
+    );
+    let total_force = attack_force + defense_force;
 
-    if total_damage == 0.0 {
-        return (0.0, 0.0);
+    if total_force == 0 {
+        return (0, 0);
     }
 
-    let to_defender = (attack_force / total_damage * attack.mul_add(4.5, ELIPSON)).round();
-    let to_attacker = (defense_force / total_damage * defense.mul_add(4.5, ELIPSON)).round();
+    let to_defender = scaled_damage(attack_force, total_force, attack);
+    let to_attacker = scaled_damage(defense_force, total_force, defense);
 
     if halved {
-        (to_attacker / 2.0, to_defender / 2.0)
+        (to_attacker / 2, to_defender / 2)
     } else {
         (to_attacker, to_defender)
     }
 }
 
 fn calculate_attacker_damage(
-    attack: f32,
-    defense: f32,
-    attacker_health_ratio: f32,
-    defender_health_ratio: f32,
-    defense_bonus: f32,
-) -> f32 {
-    let attack_force = attack * attacker_health_ratio;
-    let defense_force = defense * defender_health_ratio * defense_bonus;
-    let total_damage = attack_force + defense_force;
+    attack: u8,
+    defense: u8,
+    attacker_hp: Hp,
+    attacker_max_hp: Hp,
+    defender_hp: Hp,
+    defender_max_hp: Hp,
+    defense_bonus: u8,
+) -> Hp {
+    let (attack_force, defense_force) = forces(
+        attack,
+        defense,
+        attacker_hp,
+        attacker_max_hp,
+        defender_hp,
+        defender_max_hp,
+        defense_bonus,
+    );
+    let total_force = attack_force + defense_force;
+
+    if total_force == 0 {
+        return 0;
+    }
+
+    scaled_damage(attack_force, total_force, attack)
+}
+
+/// Whether `attacker` can reach `target` from where it stands. Units without a position are
+/// always in range of each other.
+#[inline]
+pub fn in_range(attacker: &Unit, target: &Unit) -> bool {
+    match (attacker.position, target.position) {
+        (Some(a), Some(t)) => a.distance(t) <= u16::from(attacker.range),
+        _ => true,
+    }
+}
+
+/// Damage the tentacles of `tentacled` deal to `target` when it has `target_hp` left.
+#[inline]
+fn tentacle_damage_to(tentacled: &Unit, target: &Unit, target_hp: Hp) -> Hp {
+    calculate_attacker_damage(
+        tentacled.attack,
+        target.defense,
+        tentacled.current_hp,
+        tentacled.max_hp,
+        target_hp,
+        target.max_hp,
+        target.defense_bonus,
+    )
+}
+
+/// Damage the tentacles of `tentacled` deal to `target`, e.g. when it moves next to it.
+pub fn tentacle_damage(tentacled: &Unit, target: &Unit) -> Hp {
+    tentacle_damage_to(tentacled, target, target.current_hp)
+}
+
+/// The tentacle strikes `attacker` takes from the living tentacled `defenders` next to it, as
+/// pairs of defender index and damage, in order. The defender at `target` is skipped since
+/// [`single_combat`] already accounts for its tentacles.
+///
+/// Units without a position are never next to anything.
+pub(crate) fn tentacle_strikes(
+    attacker: &Unit,
+    defenders: &Units,
+    target: usize,
+) -> ArrayVec<(usize, Hp), MAX_UNITS> {
+    let mut strikes = ArrayVec::new();
+    let Some(position) = attacker.position else {
+        return strikes;
+    };
+
+    let mut hp = attacker.current_hp;
+    for (d_idx, defender) in defenders.iter().enumerate() {
+        if hp == 0 {
+            break;
+        }
+        if d_idx == target
+            || defender.current_hp == 0
+            || !defender.skills.contains(Skills::TENTACLES)
+            || !defender.position.is_some_and(|p| p.is_adjacent(position))
+        {
+            continue;
+        }
+
+        let damage = tentacle_damage_to(defender, attacker, hp);
+        hp = hp.saturating_sub(damage);
+        strikes.push((d_idx, damage));
+    }
+
+    strikes
+}
+
+/// Applies the start-of-turn skills of every unit in `side` to the `enemies` next to it:
+/// tentacles strike them and freeze areas freeze them.
+pub fn turn_start_skills(side: &Units, enemies: &mut Units) -> CombatLog {
+    let mut log = CombatLog::new();
+
+    for unit in side.alive() {
+        let Some(position) = unit.position else {
+            continue;
+        };
+        if !unit
+            .skills
+            .intersects(Skills::TENTACLES | Skills::FREEZE_AREA)
+        {
+            continue;
+        }
+
+        for target in enemies.iter_mut() {
+            if target.current_hp == 0 || !target.position.is_some_and(|p| p.is_adjacent(position)) {
+                continue;
+            }
+
+            if unit.skills.contains(Skills::TENTACLES) {
+                let damage = tentacle_damage(unit, target);
+                log.push(CombatEvent::strike(
+                    EventKind::Tentacles,
+                    unit,
+                    target,
+                    damage,
+                    StatusEffects::empty(),
+                ));
+                target.current_hp = target.current_hp.saturating_sub(damage);
+            }
+
+            if unit.skills.contains(Skills::FREEZE_AREA) && target.current_hp > 0 {
+                log.push(CombatEvent::strike(
+                    EventKind::Freeze,
+                    unit,
+                    target,
+                    0,
+                    StatusEffects::FROZEN,
+                ));
+                target.apply_status_effects(StatusEffects::FROZEN);
+            }
+        }
+    }
+
+    log
+}
+
+/// The extra strikes an attack by `attacker` on `defenders[target]` makes on the living
+/// defenders around it, as defender index, kind and damage.
+///
+/// Splash and explosions deal half damage to everything next to the target, and stomps deal
+/// full damage to everything next to the attacker. None of them are retaliated.
+fn area_strikes(
+    attacker: &Unit,
+    defenders: &Units,
+    target: usize,
+) -> ArrayVec<(usize, EventKind, Hp), MAX_UNITS> {
+    let mut strikes = ArrayVec::new();
+
+    let (kind, center, halved) = if attacker.skills.contains(Skills::STOMP) {
+        (EventKind::Stomp, attacker.position, false)
+    } else if attacker.explodes() {
+        (EventKind::Explosion, defenders[target].position, true)
+    } else if attacker.skills.contains(Skills::SPLASH) {
+        (EventKind::Splash, defenders[target].position, true)
+    } else {
+        return strikes;
+    };
+    let Some(center) = center else {
+        return strikes;
+    };
 
-    if total_damage == 0.0 {
-        return 0.0;
+    for (d_idx, defender) in defenders.iter().enumerate() {
+        if d_idx == target
+            || defender.current_hp == 0
+            || !defender.position.is_some_and(|p| p.is_adjacent(center))
+        {
+            continue;
+        }
+
+        let (_, damage) = calculate_damage(
+            attacker.attack,
+            defender.defense,
+            attacker.current_hp,
+            attacker.max_hp,
+            defender.current_hp,
+            defender.max_hp,
+            defender.defense_bonus,
+            halved,
+        );
+        strikes.push((d_idx, kind, damage));
     }
 
-    (attack_force / total_damage * attack.mul_add(4.5, ELIPSON)).round()
+    strikes
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct UnitResult {
-    pub damage: f32,
+    pub damage: Hp,
     pub status_effects: StatusEffects,
 }
 
 pub fn single_combat(attacker: &Unit, defender: &Unit) -> (UnitResult, UnitResult) {
-    let mut tentacle_damage = 0.0;
+    let mut tentacle_damage = 0;
     let mut takes_retaliation = false;
 
-    let defender_in_range = defender.range >= attacker.range;
+    let defender_in_range = match (attacker.position, defender.position) {
+        (Some(_), Some(_)) => in_range(defender, attacker),
+        _ => defender.range >= attacker.range,
+    };
 
-    if defender.unit_type == UnitType::Jelly {
-        if attacker.unit_type == UnitType::Jelly {
+    if defender.skills.contains(Skills::TENTACLES) {
+        if attacker.skills.contains(Skills::TENTACLES) {
             takes_retaliation = true;
         } else if defender_in_range {
-            tentacle_damage = calculate_attacker_damage(
-                defender.attack,
-                attacker.defense,
-                defender.current_hp / defender.max_hp,
-                attacker.current_hp / attacker.max_hp,
-                attacker.defense_bonus,
-            );
+            tentacle_damage = tentacle_damage_to(defender, attacker, attacker.current_hp);
         }
     }
 
     let (damage_to_attacker, damage_to_defender) = calculate_damage(
         attacker.attack,
         defender.defense,
-        (attacker.current_hp - tentacle_damage) / attacker.max_hp,
-        defender.current_hp / defender.max_hp,
+        attacker.current_hp.saturating_sub(tentacle_damage),
+        attacker.max_hp,
+        defender.current_hp,
+        defender.max_hp,
         defender.defense_bonus,
-        attacker
-            .status_effects
-            .contains(StatusEffects::SPLASHING | StatusEffects::EXPLODING),
+        // The target of an explosion takes half damage like everything else next to it.
+        attacker.explodes(),
     );
 
-    let effects_to_defender = attacker.trait_effects;
+    let effects_to_defender = attacker.skills.inflicted();
 
     takes_retaliation = takes_retaliation
         || attacker
             .status_effects
             .contains(StatusEffects::TAKES_RETALIATION)
         || !(!defender_in_range
-            || (defender.current_hp - damage_to_defender) <= 0.0
+            || damage_to_defender >= defender.current_hp
             || !defender.retaliates
             || attacker.surprise
-            || effects_to_defender.contains(StatusEffects::FROZEN)
-            || effects_to_defender.contains(StatusEffects::CONVERTED)
+            || effects_to_defender.intersects(StatusEffects::FROZEN | StatusEffects::CONVERTED)
             || defender.status_effects.contains(StatusEffects::FROZEN));
 
-    let effects_to_attacker = defender.trait_effects;
+    let effects_to_attacker = defender.skills.inflicted();
 
-    let exploding = attacker.status_effects.contains(StatusEffects::EXPLODING);
+    let exploding = attacker.explodes();
 
     // Premature optimization to avoid branches
     //
-    // let total_damage_to_attacker = (damage_to_attacker * f32::from(takes_retaliation)
+    // let total_damage_to_attacker = (damage_to_attacker * Hp::from(takes_retaliation)
     //     + tentacle_damage)
-    //     * f32::from(!exploding)
-    //     + f32::from(exploding) * attacker.max_hp;
+    //     * Hp::from(!exploding)
+    //     + Hp::from(exploding) * attacker.max_hp;
 
     let total_damage_to_attacker = if exploding {
         attacker.max_hp
@@ -139,44 +357,209 @@ pub fn single_combat(attacker: &Unit, defender: &Unit) -> (UnitResult, UnitResul
     )
 }
 
-pub fn multi_combat_score(attackers: &Units, mut defenders: Units) -> f32 {
+pub fn multi_combat_score(attackers: &Units, defenders: Units) -> i32 {
+    multi_combat_score_sparing(attackers, defenders, &[])
+}
+
+/// Like [`multi_combat_score`], but no attacker picks the defenders at the indices in
+/// `spared` as its target. They stay on the board, so their tentacles still strike and area
+/// damage still hits them.
+pub fn multi_combat_score_sparing(
+    attackers: &Units,
+    mut defenders: Units,
+    spared: &[usize],
+) -> i32 {
     let n_defenders = defenders.len();
-    let mut score = 0.0;
+    let mut score = 0;
     let mut d_idx = 0;
+    // Hp of the attackers, kept up to date for segments that die with their head.
+    let mut hp: ArrayVec<Hp, MAX_UNITS> = attackers.iter().map(|unit| unit.current_hp).collect();
 
     for a_idx in 0..attackers.len() {
-        let attacker = &attackers[a_idx];
-        if d_idx >= n_defenders {
-            break;
+        if hp[a_idx] == 0 {
+            continue;
         }
+        let mut attacker = Cow::Borrowed(&attackers[a_idx]);
+        let mut moved = false;
+
+        loop {
+            // Area damage can take out defenders further down the order.
+            while d_idx < n_defenders
+                && (defenders[d_idx].current_hp == 0 || spared.contains(&d_idx))
+            {
+                d_idx += 1;
+            }
 
-        let defender = &mut defenders[d_idx];
+            // Persisting onto a new tile gets the attacker struck by the tentacles next to it.
+            if moved {
+                for (_, damage) in tentacle_strikes(&attacker, &defenders, d_idx) {
+                    let attacker = attacker.to_mut();
+                    score -= i32::from(damage.min(attacker.current_hp));
+                    attacker.current_hp = attacker.current_hp.saturating_sub(damage);
+                }
+                hp[a_idx] = attacker.current_hp;
+                if hp[a_idx] == 0 {
+                    break;
+                }
+            }
+            if d_idx >= n_defenders || !in_range(&attacker, &defenders[d_idx]) {
+                break;
+            }
+
+            let area = area_strikes(&attacker, &defenders, d_idx);
+            let defender = &mut defenders[d_idx];
+
+            let (to_attacker, to_defender) = single_combat(&attacker, defender);
+
+            score -= i32::from(to_attacker.damage.min(attacker.current_hp));
+            score += i32::from(to_defender.damage.min(defender.current_hp));
+
+            let killed = to_defender.damage >= defender.current_hp;
+            // Converted defenders leave the defending side just like killed ones.
+            if killed
+                || to_defender
+                    .status_effects
+                    .contains(StatusEffects::CONVERTED)
+            {
+                defender.current_hp = 0;
+                score += 100;
+            } else {
+                defender.current_hp -= to_defender.damage;
+                defender.apply_status_effects(to_defender.status_effects);
+            }
+            let target = defender.position;
+            if killed {
+                score += severed_score(&mut defenders, d_idx);
+            }
 
-        let (to_attacker, to_defender) = single_combat(attacker, defender);
+            for (v_idx, _, damage) in area {
+                let victim = &mut defenders[v_idx];
+                score += i32::from(damage.min(victim.current_hp));
+                if damage >= victim.current_hp {
+                    victim.current_hp = 0;
+                    score += 100 + severed_score(&mut defenders, v_idx);
+                } else {
+                    victim.current_hp -= damage;
+                }
+            }
 
-        score -= to_attacker.damage.min(attacker.current_hp);
-        score += to_defender.damage.min(defender.current_hp);
+            hp[a_idx] = attacker.current_hp.saturating_sub(to_attacker.damage);
+            if !killed || !attacker.skills.contains(Skills::PERSIST) || hp[a_idx] == 0 {
+                break;
+            }
+            let attacker = attacker.to_mut();
+            attacker.current_hp = hp[a_idx];
+            attacker.apply_status_effects(to_attacker.status_effects);
+            if attacker.range == 1 {
+                let start = attacker.position;
+                attacker.position = target.or(start);
+                moved = attacker.position != start;
+            }
+        }
 
-        if to_defender.damage >= defender.current_hp {
-            d_idx += 1;
-            score += 100.0;
-        } else {
-            defender.current_hp -= to_defender.damage;
-            defender.apply_status_effects(to_defender.status_effects);
+        if hp[a_idx] == 0
+            && attacker.skills.contains(Skills::GROW)
+            && let Some(body) = attacker.body
+        {
+            for (i, unit) in attackers.iter().enumerate() {
+                if i != a_idx && unit.body == Some(body) {
+                    score -= i32::from(hp[i]);
+                    hp[i] = 0;
+                }
+            }
         }
     }
 
     score
 }
 
+/// Kills the segments that die with `defenders[head]`, scoring them like kills.
+fn severed_score(defenders: &mut Units, head: usize) -> i32 {
+    defenders
+        .sever(head)
+        .into_iter()
+        .map(|(_, hp)| i32::from(hp) + 100)
+        .sum()
+}
+
+/// What kind of strike a [`CombatEvent`] records.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub enum EventKind {
+    /// A regular attack, with retaliation if any.
+    #[default]
+    Attack,
+    /// A tentacle strike on a unit next to the tentacled attacker. It is never retaliated.
+    Tentacles,
+    /// Half damage to a unit next to the target of a splashing attack.
+    Splash,
+    /// Half damage to a unit next to the target of an exploding attacker.
+    Explosion,
+    /// Damage to a unit next to a stomping attacker.
+    Stomp,
+    /// A unit next to a unit with a freeze area is frozen.
+    Freeze,
+    /// A segment grows onto the body of the centipede that made a kill.
+    Grow,
+    /// A defending segment dies with its head. It counts as a kill.
+    Severed,
+    /// An attacking segment dies with its head.
+    SegmentLost,
+}
+
 #[derive(Debug)]
 pub struct CombatEvent {
+    pub kind: EventKind,
+    /// The attacker as it was when it attacked, standing where it attacked from.
     pub attacker: Unit,
     pub defender: Unit,
-    pub damage_to_attacker: f32,
-    pub damage_to_defender: f32,
+    pub damage_to_attacker: Hp,
+    pub damage_to_defender: Hp,
     pub status_effects_to_attacker: StatusEffects,
     pub status_effects_to_defender: StatusEffects,
+    /// Where the attacker moved from before attacking, if it moved.
+    pub moved_from: Option<Position>,
+    /// The attacker's kill count after this attack.
+    pub kills: u8,
+}
+
+impl CombatEvent {
+    /// A strike from `striker` on `target` that cannot be retaliated, like a tentacle strike
+    /// or splash damage.
+    pub fn strike(
+        kind: EventKind,
+        striker: &Unit,
+        target: &Unit,
+        damage: Hp,
+        status_effects: StatusEffects,
+    ) -> Self {
+        Self {
+            kind,
+            attacker: striker.clone(),
+            defender: target.clone(),
+            damage_to_attacker: 0,
+            damage_to_defender: damage,
+            status_effects_to_attacker: StatusEffects::empty(),
+            status_effects_to_defender: status_effects,
+            moved_from: None,
+            kills: striker
+                .kills
+                .saturating_add(u8::from(damage >= target.current_hp)),
+        }
+    }
+
+    pub fn is_kill(&self) -> bool {
+        self.damage_to_defender >= self.defender.current_hp && self.kind != EventKind::Freeze
+    }
+
+    /// Whether this is the kill that makes the attacker eligible for promotion.
+    pub fn earns_promotion(&self) -> bool {
+        self.is_kill()
+            && self.kills == KILLS_TO_PROMOTE
+            && !self
+                .attacker
+                .status_effects
+                .contains(StatusEffects::VETERAN)
+    }
 }
 
 #[derive(Debug)]
@@ -186,6 +569,35 @@ impl CombatLog {
     pub fn new() -> Self {
         Self(Vec::new())
     }
+
+    /// Scores the log the same way as [`multi_combat_score`].
+    ///
+    /// Tentacle strikes count against the attackers, since only defenders' tentacles strike
+    /// during a volley.
+    pub fn score(&self) -> i32 {
+        self.iter()
+            .map(|event| {
+                match event.kind {
+                    EventKind::Tentacles | EventKind::SegmentLost => {
+                        return -i32::from(event.damage_to_defender.min(event.defender.current_hp));
+                    }
+                    EventKind::Freeze | EventKind::Grow => return 0,
+                    _ => {}
+                }
+
+                let mut score = i32::from(event.damage_to_defender.min(event.defender.current_hp))
+                    - i32::from(event.damage_to_attacker.min(event.attacker.current_hp));
+                if event.is_kill()
+                    || event
+                        .status_effects_to_defender
+                        .contains(StatusEffects::CONVERTED)
+                {
+                    score += 100;
+                }
+                score
+            })
+            .sum()
+    }
 }
 
 impl Default for CombatLog {
@@ -194,6 +606,15 @@ impl Default for CombatLog {
     }
 }
 
+impl IntoIterator for CombatLog {
+    type Item = CombatEvent;
+    type IntoIter = std::vec::IntoIter<CombatEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
 impl std::ops::Deref for CombatLog {
     type Target = Vec<CombatEvent>;
     fn deref(&self) -> &Self::Target {
@@ -207,47 +628,223 @@ impl std::ops::DerefMut for CombatLog {
     }
 }
 
-pub fn multi_combat_log(attackers: &Units, mut defenders: Units) -> CombatLog {
+/// Both sides as they are after a volley, together with its log.
+#[derive(Debug)]
+pub struct Outcome {
+    pub log: CombatLog,
+    /// The attackers after taking retaliation, in the order they were given, followed by any
+    /// segments they grew.
+    pub attackers: Units,
+    /// Whether each attacker took part in the volley. Segments grown during the volley are
+    /// added after the attackers and never act.
+    pub acted: Vec<bool>,
+    /// Defenders converted to the attacking side, as they were when converted.
+    pub converted: Units,
+    /// The defenders after the volley. Killed and converted defenders are left with no hp.
+    pub defenders: Units,
+}
+
+pub fn multi_combat_log(attackers: &Units, defenders: Units) -> CombatLog {
+    multi_combat_outcome(attackers, defenders).log
+}
+
+/// Runs the same volley as [`multi_combat_log`], also applying damage, status effects and
+/// kills to both sides.
+pub fn multi_combat_outcome(attackers: &Units, defenders: Units) -> Outcome {
+    multi_combat_outcome_sparing(attackers, defenders, &[])
+}
+
+/// Like [`multi_combat_outcome`], but spares the defenders at the indices in `spared` like
+/// [`multi_combat_score_sparing`].
+pub fn multi_combat_outcome_sparing(
+    attackers: &Units,
+    mut defenders: Units,
+    spared: &[usize],
+) -> Outcome {
     let mut log = CombatLog::new();
+    let mut survivors = attackers.clone();
+    let mut acted = vec![false; attackers.len()];
+    let mut converted = Units::new();
     let mut d_idx = 0;
 
     let n_defenders = defenders.len();
 
     for a_idx in 0..attackers.len() {
-        let attacker = &attackers[a_idx];
-        if d_idx >= n_defenders {
-            break;
-        }
+        let mut moved = false;
+
+        loop {
+            // Area damage can take out defenders further down the order.
+            while d_idx < n_defenders
+                && (defenders[d_idx].current_hp == 0 || spared.contains(&d_idx))
+            {
+                d_idx += 1;
+            }
 
-        let defender = &mut defenders[d_idx];
+            // Persisting onto a new tile gets the attacker struck by the tentacles next to it.
+            let survivor = &mut survivors[a_idx];
+            if moved {
+                for (t_idx, damage) in tentacle_strikes(survivor, &defenders, d_idx) {
+                    log.push(CombatEvent::strike(
+                        EventKind::Tentacles,
+                        &defenders[t_idx],
+                        survivor,
+                        damage,
+                        StatusEffects::empty(),
+                    ));
+                    survivor.current_hp = survivor.current_hp.saturating_sub(damage);
+                }
+            }
+            if d_idx >= n_defenders
+                || survivor.current_hp == 0
+                || !in_range(survivor, &defenders[d_idx])
+            {
+                break;
+            }
 
-        let (to_attacker, to_defender) = single_combat(attacker, defender);
+            acted[a_idx] = true;
+            let attacker = survivor.clone();
+            let area = area_strikes(&attacker, &defenders, d_idx);
+            let defender = &mut defenders[d_idx];
+
+            let (to_attacker, to_defender) = single_combat(&attacker, defender);
+            let killed = to_defender.damage >= defender.current_hp;
+
+            survivor.current_hp = survivor.current_hp.saturating_sub(to_attacker.damage);
+            survivor.apply_status_effects(to_attacker.status_effects);
+            survivor.kills = survivor.kills.saturating_add(u8::from(killed));
+
+            log.push(CombatEvent {
+                kind: EventKind::Attack,
+                attacker: attacker.clone(),
+                defender: defender.clone(),
+                damage_to_attacker: to_attacker.damage,
+                damage_to_defender: to_defender.damage,
+                status_effects_to_attacker: to_attacker.status_effects,
+                status_effects_to_defender: to_defender.status_effects,
+                moved_from: None,
+                kills: survivor.kills,
+            });
+
+            let mut kills = ArrayVec::<(usize, Option<Position>), MAX_UNITS>::new();
+            if killed {
+                defender.current_hp = 0;
+                kills.push((d_idx, defender.position));
+            } else if to_defender
+                .status_effects
+                .contains(StatusEffects::CONVERTED)
+            {
+                let mut convert = defender.clone();
+                convert.current_hp -= to_defender.damage;
+                convert.status_effects -= StatusEffects::CONVERTED;
+                converted.push(convert);
+                defender.current_hp = 0;
+            } else {
+                defender.current_hp -= to_defender.damage;
+                defender.apply_status_effects(to_defender.status_effects);
+            }
+            let target = defender.position;
+
+            // Later events are built from the running state so their kill counts add up.
+            for (v_idx, kind, damage) in area {
+                let victim = &mut defenders[v_idx];
+                log.push(CombatEvent::strike(
+                    kind,
+                    survivor,
+                    victim,
+                    damage,
+                    StatusEffects::empty(),
+                ));
+                if damage >= victim.current_hp {
+                    victim.current_hp = 0;
+                    survivor.kills = survivor.kills.saturating_add(1);
+                    kills.push((v_idx, victim.position));
+                } else {
+                    victim.current_hp -= damage;
+                }
+            }
 
-        log.push(CombatEvent {
-            attacker: attacker.clone(),
-            defender: defender.clone(),
-            damage_to_attacker: to_attacker.damage,
-            damage_to_defender: to_defender.damage,
-            status_effects_to_attacker: to_attacker.status_effects,
-            status_effects_to_defender: to_defender.status_effects,
-        });
+            let grows = attacker.skills.contains(Skills::GROW) && survivor.current_hp > 0;
+            for (k_idx, position) in kills {
+                for (s_idx, hp) in defenders.sever(k_idx) {
+                    let segment = defenders[s_idx].clone().with_current_hp(hp);
+                    let survivor = &mut survivors[a_idx];
+                    log.push(CombatEvent::strike(
+                        EventKind::Severed,
+                        survivor,
+                        &segment,
+                        hp,
+                        StatusEffects::empty(),
+                    ));
+                    survivor.kills = survivor.kills.saturating_add(1);
+                }
+
+                // Segments grow onto the tile of the unit the head killed.
+                if let Some(s_idx) = grows.then(|| survivors.grow(a_idx)).flatten() {
+                    survivors[s_idx].position = position;
+                    acted.push(false);
+                    log.push(CombatEvent::strike(
+                        EventKind::Grow,
+                        &survivors[a_idx],
+                        &survivors[s_idx],
+                        0,
+                        StatusEffects::empty(),
+                    ));
+                }
+            }
 
-        if to_defender.damage >= defender.current_hp {
-            d_idx += 1;
-        } else {
-            defender.current_hp -= to_defender.damage;
-            defender.apply_status_effects(to_defender.status_effects);
+            let survivor = &survivors[a_idx];
+            if !killed || !survivor.skills.contains(Skills::PERSIST) || survivor.current_hp == 0 {
+                break;
+            }
+            let survivor = &mut survivors[a_idx];
+            if survivor.range == 1 {
+                let start = survivor.position;
+                survivor.position = target.or(start);
+                moved = survivor.position != start;
+            }
+        }
+
+        if survivors[a_idx].current_hp == 0 {
+            let head = survivors[a_idx].clone();
+            for (s_idx, hp) in survivors.sever(a_idx) {
+                let segment = survivors[s_idx].clone().with_current_hp(hp);
+                log.push(CombatEvent::strike(
+                    EventKind::SegmentLost,
+                    &head,
+                    &segment,
+                    hp,
+                    StatusEffects::empty(),
+                ));
+            }
         }
     }
 
-    log
+    Outcome {
+        log,
+        attackers: survivors,
+        acted,
+        converted,
+        defenders,
+    }
 }
 
-pub fn optimized(mut attackers: Units, mut defenders: Units) -> (f32, CombatLog) {
+/// Finds the attacker and defender orders with the highest [`multi_combat_score`].
+pub fn optimized_order(attackers: Units, defenders: Units) -> (i32, Units, Units) {
+    optimized_order_by(attackers, defenders, multi_combat_score)
+}
+
+/// Finds the attacker and defender orders with the highest `score`.
+///
+/// `score` is called like [`multi_combat_score`] for every pair of orders.
+pub fn optimized_order_by(
+    mut attackers: Units,
+    mut defenders: Units,
+    mut score: impl FnMut(&Units, Units) -> i32,
+) -> (i32, Units, Units) {
     let attacker_pairs = Perms::new(attackers.len());
     let n_defenders = defenders.len();
 
-    let mut top_score = f32::MIN;
+    let mut top_score = i32::MIN;
     let mut best_attacker_order: Units = Units::new();
     let mut best_defender_order: Units = Units::new();
     for (a_first, a_second) in attacker_pairs {
@@ -257,7 +854,7 @@ pub fn optimized(mut attackers: Units, mut defenders: Units) -> (f32, CombatLog)
         for (d_first, d_second) in defenders_perms {
             defenders.swap(d_first, d_second);
 
-            let score = multi_combat_score(&attackers, defenders.clone());
+            let score = score(&attackers, defenders.clone());
             if score > top_score {
                 top_score = score;
                 best_attacker_order = attackers.clone();
@@ -266,78 +863,271 @@ pub fn optimized(mut attackers: Units, mut defenders: Units) -> (f32, CombatLog)
         }
     }
 
+    (top_score, best_attacker_order, best_defender_order)
+}
+
+pub fn optimized(attackers: Units, defenders: Units) -> (i32, CombatLog) {
+    optimized_by(attackers, defenders, multi_combat_score)
+}
+
+/// Like [`optimized`], but ranks orders with `score` instead of [`multi_combat_score`].
+pub fn optimized_by(
+    attackers: Units,
+    defenders: Units,
+    score: impl FnMut(&Units, Units) -> i32,
+) -> (i32, CombatLog) {
+    let (top_score, best_attacker_order, best_defender_order) =
+        optimized_order_by(attackers, defenders, score);
+
     (
         top_score,
         multi_combat_log(&best_attacker_order, best_defender_order),
     )
 }
 
+/// Projects the best strike the defenders left standing after `outcome` can make against
+/// the attackers left standing, scored from the defenders' side.
+///
+/// Frozen defenders and defenders without attack sit the strike out.
+pub fn counterattack(outcome: &Outcome) -> (i32, CombatLog) {
+    let strikers: Units = outcome
+        .defenders
+        .alive()
+        .filter(|unit| unit.attack > 0 && !unit.status_effects.contains(StatusEffects::FROZEN))
+        .cloned()
+        .collect();
+    let targets: Units = outcome.attackers.alive().cloned().collect();
+
+    if strikers.is_empty() || targets.is_empty() {
+        return (0, CombatLog::new());
+    }
+
+    optimized(strikers, targets)
+}
+
+/// Like [`optimized`], but subtracts the score of the defenders' best [`counterattack`] from
+/// every order, so that plans leaving the attackers exposed rank lower.
+///
+/// This runs a full optimization for every order, so it is only practical for small fights.
+pub fn optimized_with_counterattack(attackers: Units, defenders: Units) -> (i32, CombatLog) {
+    optimized_by(attackers, defenders, |attackers, defenders| {
+        let outcome = multi_combat_outcome(attackers, defenders);
+        outcome.log.score() - counterattack(&outcome).0
+    })
+}
+
 pub fn bulk(attacker: &Unit, mut defender: Unit) -> u32 {
     let mut n_attacks = 0;
 
-    while defender.current_hp > 0.0 {
+    while defender.current_hp > 0 {
         n_attacks += 1;
 
         let damage_to_defender = calculate_attacker_damage(
             attacker.attack,
             defender.defense,
-            attacker.current_hp / attacker.max_hp,
-            defender.current_hp / defender.max_hp,
+            attacker.current_hp,
+            attacker.max_hp,
+            defender.current_hp,
+            defender.max_hp,
             defender.defense_bonus,
         );
 
-        defender.current_hp -= damage_to_defender;
-        defender.apply_status_effects(attacker.trait_effects);
+        defender.current_hp = defender.current_hp.saturating_sub(damage_to_defender);
+        defender.apply_status_effects(attacker.skills.inflicted());
     }
 
     n_attacks
 }
 
-pub fn eliminate_defender(attacker: &Unit, mut defender: Unit) -> f32 {
-    let mut i: u16 = 0;
+/// One hit of a [`BulkReport`], with the hp both units had before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    pub attacker_hp: Hp,
+    pub defender_hp: Hp,
+    pub damage_dealt: Hp,
+    pub damage_received: Hp,
+}
 
-    loop {
-        defender.current_hp = defender.max_hp - i as f32;
+impl Hit {
+    pub fn is_kill(&self) -> bool {
+        self.damage_dealt >= self.defender_hp
+    }
 
-        let damage_to_defender = calculate_attacker_damage(
-            attacker.attack,
-            defender.defense,
-            attacker.current_hp / attacker.max_hp,
-            defender.current_hp / defender.max_hp,
-            defender.defense_bonus,
-        );
+    /// Whether the attacker died to retaliation.
+    pub fn is_loss(&self) -> bool {
+        self.damage_received >= self.attacker_hp
+    }
+}
+
+/// The hits made on one defender by [`bulk_copies`] or [`bulk_repeated`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BulkReport {
+    pub hits: Vec<Hit>,
+    /// Whether the last hit killed or converted the defender.
+    pub killed: bool,
+}
+
+impl BulkReport {
+    /// Attackers that died to retaliation.
+    pub fn attackers_lost(&self) -> usize {
+        self.hits.iter().filter(|hit| hit.is_loss()).count()
+    }
+}
+
+/// Makes one hit with `attacker` on `defender`, applying its damage and status effects to
+/// both. Returns the hit and whether the defender is gone.
+fn hit(attacker: &mut Unit, defender: &mut Unit) -> (Hit, bool) {
+    let (to_attacker, to_defender) = single_combat(attacker, defender);
+    let hit = Hit {
+        attacker_hp: attacker.current_hp,
+        defender_hp: defender.current_hp,
+        damage_dealt: to_defender.damage,
+        damage_received: to_attacker.damage,
+    };
+
+    attacker.current_hp = attacker.current_hp.saturating_sub(to_attacker.damage);
+    attacker.apply_status_effects(to_attacker.status_effects);
+    defender.current_hp = defender.current_hp.saturating_sub(to_defender.damage);
+    defender.apply_status_effects(to_defender.status_effects);
 
-        if defender.current_hp <= damage_to_defender {
+    let gone = defender.current_hp == 0
+        || to_defender
+            .status_effects
+            .contains(StatusEffects::CONVERTED);
+    (hit, gone)
+}
+
+/// Attacks `defender` with fresh copies of `attacker` until it dies, answering how many
+/// copies it takes. Every copy takes its own retaliation, and the status effects each hit
+/// applies to the defender stay for the next.
+///
+/// Fails if the copies can never kill the defender.
+pub fn bulk_copies(attacker: &Unit, mut defender: Unit) -> Result<BulkReport, EliminateError> {
+    if attacker.attack == 0 && !attacker.skills.contains(Skills::CONVERT) {
+        return Err(EliminateError::NoAttack);
+    }
+
+    let mut report = BulkReport::default();
+    while !report.killed {
+        let effects = defender.status_effects;
+        let (hit, gone) = hit(&mut attacker.clone(), &mut defender);
+        // Every copy is fresh, so a hit that deals no damage and inflicts nothing new leaves
+        // the defender as it was, and every later copy would repeat it.
+        if hit.damage_dealt == 0 && !gone && defender.status_effects == effects {
+            return Err(EliminateError::TooStrong);
+        }
+        report.hits.push(hit);
+        report.killed = gone;
+    }
+
+    Ok(report)
+}
+
+/// Attacks `defender` with `attacker` once a turn until one of them dies, with the attacker
+/// carrying its retaliation damage from turn to turn. Neither unit heals in between.
+///
+/// The report ends early, without a kill, once the attacker stops dealing damage.
+pub fn bulk_repeated(mut attacker: Unit, mut defender: Unit) -> Result<BulkReport, EliminateError> {
+    if attacker.attack == 0 && !attacker.skills.contains(Skills::CONVERT) {
+        return Err(EliminateError::NoAttack);
+    }
+
+    let mut report = BulkReport::default();
+    while attacker.current_hp > 0 && !report.killed {
+        let (hit, gone) = hit(&mut attacker, &mut defender);
+        if hit.damage_dealt == 0 && !gone {
             break;
         }
-        i += 1;
+        report.hits.push(hit);
+        report.killed = gone;
     }
 
-    defender.max_hp - (i as f32)
+    Ok(report)
 }
 
-pub fn eliminate_attacker(mut attacker: Unit, defender: &Unit) -> f32 {
-    let mut i: u16 = 0;
+/// Why an attacker can never kill a defender in one hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EliminateError {
+    /// The attacker has no attack and does not convert.
+    NoAttack,
+    /// Not even the most favourable hp gets a kill.
+    TooStrong,
+}
 
-    loop {
-        attacker.current_hp = i as f32;
+impl fmt::Display for EliminateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAttack => write!(f, "the attacker cannot attack"),
+            Self::TooStrong => write!(f, "the attacker can never kill the defender in one hit"),
+        }
+    }
+}
 
-        let damage_to_defender = calculate_attacker_damage(
-            attacker.attack,
-            defender.defense,
-            attacker.current_hp / attacker.max_hp,
-            defender.current_hp / defender.max_hp,
-            defender.defense_bonus,
-        );
+impl std::error::Error for EliminateError {}
 
-        if defender.current_hp <= damage_to_defender {
-            break;
+/// Binary searches for the lowest hp in `low..=high` for which `holds` is true, assuming
+/// that once it is true it stays true for every higher hp.
+fn lowest_hp_where(low: Hp, high: Hp, mut holds: impl FnMut(Hp) -> bool) -> Option<Hp> {
+    let (mut low, mut high) = (u32::from(low), u32::from(high) + 1);
+    let end = high;
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        // `mid` is below `end`, which is at most `Hp::MAX + 1`.
+        if holds(mid as Hp) {
+            high = mid;
+        } else {
+            low = mid + 1;
         }
+    }
+
+    (low < end).then_some(low as Hp)
+}
+
+/// Finds the highest hp the defender can have and still be killed by one hit from
+/// `attacker`, ignoring the defender's current hp.
+///
+/// Attackers that convert eliminate defenders at any hp.
+pub fn eliminate_defender(attacker: &Unit, defender: Unit) -> Result<Hp, EliminateError> {
+    if attacker.skills.contains(Skills::CONVERT) {
+        return Ok(defender.max_hp);
+    }
+    if attacker.attack == 0 {
+        return Err(EliminateError::NoAttack);
+    }
+
+    // Damage only drops as the defender's hp rises, so the defender survives from some hp on.
+    let survives = |hp: Hp| {
+        let defender = defender.clone().with_current_hp(hp);
+        single_combat(attacker, &defender).1.damage < hp
+    };
+    match lowest_hp_where(1, defender.max_hp, survives) {
+        Some(1) => Err(EliminateError::TooStrong),
+        Some(hp) => Ok(hp - 1),
+        None => Ok(defender.max_hp),
+    }
+}
 
-        i += 1;
+/// Finds the lowest hp `attacker` needs to kill `defender` at its current hp in one hit.
+///
+/// Attackers that convert eliminate the defender at any hp.
+pub fn eliminate_attacker(attacker: Unit, defender: &Unit) -> Result<Hp, EliminateError> {
+    if defender.current_hp == 0 {
+        return Ok(0);
+    }
+    if attacker.skills.contains(Skills::CONVERT) {
+        return Ok(1);
+    }
+    if attacker.attack == 0 {
+        return Err(EliminateError::NoAttack);
     }
 
-    i as f32
+    // Damage only grows with the attacker's hp.
+    let kills = |hp: Hp| {
+        let attacker = attacker.clone().with_current_hp(hp);
+        single_combat(&attacker, defender).1.damage >= defender.current_hp
+    };
+    lowest_hp_where(1, attacker.max_hp, kills).ok_or(EliminateError::TooStrong)
 }
 
 #[cfg(test)]
@@ -353,8 +1143,8 @@ mod tests {
 
         let (attacker_result, defender_result) = single_combat(&attacker, &defender);
 
-        assert_eq!(attacker_result.damage, 5.0);
-        assert_eq!(defender_result.damage, 5.0);
+        assert_eq!(attacker_result.damage, 5);
+        assert_eq!(defender_result.damage, 5);
         assert_eq!(attacker_result.status_effects, StatusEffects::empty());
         assert_eq!(defender_result.status_effects, StatusEffects::empty());
     }
@@ -366,8 +1156,8 @@ mod tests {
 
         let (attacker_result, defender_result) = single_combat(&attacker, &defender);
 
-        assert_eq!(attacker_result.damage, 5.0);
-        assert_eq!(defender_result.damage, 4.0);
+        assert_eq!(attacker_result.damage, 5);
+        assert_eq!(defender_result.damage, 4);
         assert_eq!(attacker_result.status_effects, StatusEffects::empty());
         assert_eq!(defender_result.status_effects, StatusEffects::empty());
     }
@@ -377,16 +1167,27 @@ mod tests {
         let attacker = Unit::new(UnitType::Warrior);
         let defender = Unit::new(UnitType::Warrior)
             .with_status_effects(StatusEffects::FORTIFIED)
-            .with_current_hp(6.0);
+            .with_current_hp(6);
 
         let (attacker_result, defender_result) = single_combat(&attacker, &defender);
 
-        assert_eq!(attacker_result.damage, 4.0);
-        assert_eq!(defender_result.damage, 5.0);
+        assert_eq!(attacker_result.damage, 4);
+        assert_eq!(defender_result.damage, 5);
         assert_eq!(attacker_result.status_effects, StatusEffects::empty());
         assert_eq!(defender_result.status_effects, StatusEffects::empty());
     }
 
+    #[test]
+    fn test_wa_cl() {
+        let attacker = Unit::new(UnitType::Warrior);
+        let defender = Unit::new(UnitType::Cloak);
+
+        let (attacker_result, defender_result) = single_combat(&attacker, &defender);
+
+        assert_eq!(attacker_result.damage, 0);
+        assert_eq!(defender_result.damage, 7);
+    }
+
     #[test]
     fn test_wa_je() {
         let attacker = Unit::new(UnitType::Warrior);
@@ -394,8 +1195,8 @@ mod tests {
 
         let (attacker_result, defender_result) = single_combat(&attacker, &defender);
 
-        assert_eq!(attacker_result.damage, 5.0);
-        assert_eq!(defender_result.damage, 3.0);
+        assert_eq!(attacker_result.damage, 5);
+        assert_eq!(defender_result.damage, 3);
         assert_eq!(attacker_result.status_effects, StatusEffects::empty());
         assert_eq!(defender_result.status_effects, StatusEffects::empty());
     }
@@ -407,24 +1208,37 @@ mod tests {
 
         let (attacker_result, defender_result) = single_combat(&attacker, &defender);
 
-        assert_eq!(attacker_result.damage, 5.0);
-        assert_eq!(defender_result.damage, 5.0);
+        assert_eq!(attacker_result.damage, 5);
+        assert_eq!(defender_result.damage, 5);
         assert_eq!(attacker_result.status_effects, StatusEffects::empty());
         assert_eq!(defender_result.status_effects, StatusEffects::empty());
     }
 
     #[test]
     fn test_je_4_wa() {
-        let attacker = Unit::new(UnitType::Jelly).with_current_hp(4.0);
+        let attacker = Unit::new(UnitType::Jelly).with_current_hp(4);
         let defender = Unit::new(UnitType::Warrior);
 
         let (attacker_result, defender_result) = single_combat(&attacker, &defender);
-        assert_eq!(attacker_result.damage, 8.0);
-        assert_eq!(defender_result.damage, 2.0);
+        assert_eq!(attacker_result.damage, 8);
+        assert_eq!(defender_result.damage, 2);
         assert_eq!(attacker_result.status_effects, StatusEffects::empty());
         assert_eq!(defender_result.status_effects, StatusEffects::empty());
     }
 
+    #[test]
+    fn test_ar_wa_positions() {
+        let attacker = Unit::new(UnitType::Archer).with_position(Position::new(0, 0));
+        let adjacent = Unit::new(UnitType::Warrior).with_position(Position::new(1, 1));
+        let distant = Unit::new(UnitType::Warrior).with_position(Position::new(2, 0));
+
+        let (attacker_result, _) = single_combat(&attacker, &adjacent);
+        assert!(attacker_result.damage > 0);
+
+        let (attacker_result, _) = single_combat(&attacker, &distant);
+        assert_eq!(attacker_result.damage, 0);
+    }
+
     #[test]
     fn test_wa_wa_vs_wa_d() {
         let attackers = Units::from([Unit::new(UnitType::Warrior), Unit::new(UnitType::Warrior)]);
@@ -433,7 +1247,7 @@ mod tests {
         ]);
         let score = multi_combat_score(&attackers, defenders);
 
-        assert_eq!(score, 0.0);
+        assert_eq!(score, 0);
     }
 
     #[test]
@@ -448,7 +1262,7 @@ mod tests {
         ]);
         let score = multi_combat_score(&attackers, defenders);
 
-        assert_eq!(score, 1.0);
+        assert_eq!(score, 101);
     }
 
     #[test]
@@ -463,25 +1277,407 @@ mod tests {
         let defenders = Units::from([Unit::new(UnitType::Jelly)]);
 
         let score = multi_combat_score(&attackers, defenders);
-        assert_eq!(score, 2.0);
+        assert_eq!(score, 102);
     }
 
     #[test]
     fn test_ri_6_wa_ar_3_kn_ca_5_v_ca_vs_gi() {
         let attackers = Units::from([
-            Unit::new(UnitType::Rider).with_current_hp(6.0),
+            Unit::new(UnitType::Rider).with_current_hp(6),
             Unit::new(UnitType::Warrior),
-            Unit::new(UnitType::Archer).with_current_hp(3.0),
+            Unit::new(UnitType::Archer).with_current_hp(3),
             Unit::new(UnitType::Knight),
             Unit::new(UnitType::Catapult)
-                .with_current_hp(5.0)
+                .with_current_hp(5)
                 .with_status_effects(StatusEffects::VETERAN),
             Unit::new(UnitType::Catapult),
         ]);
         let defenders = Units::from([Unit::new(UnitType::Giant)]);
 
         let (score, _log) = optimized(attackers, defenders);
-        assert_eq!(score, 17.0);
+        assert_eq!(score, 117);
+    }
+
+    #[test]
+    fn test_log_score_matches() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Warrior),
+            Unit::new(UnitType::Warrior),
+            Unit::new(UnitType::Warrior),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_status_effects(StatusEffects::FORTIFIED)
+        ]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        assert_eq!(multi_combat_log(&attackers, defenders).score(), score);
+    }
+
+    #[test]
+    fn test_counterattack_ar_vs_gi() {
+        let attackers = Units::from([Unit::new(UnitType::Archer)]);
+        let defenders = Units::from([Unit::new(UnitType::Giant)]);
+
+        let outcome = multi_combat_outcome(&attackers, defenders);
+        let (score, log) = counterattack(&outcome);
+
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].attacker.unit_type, UnitType::Giant);
+        assert_eq!(log[0].defender.current_hp, outcome.attackers[0].current_hp);
+        assert!(score > 100);
+    }
+
+    #[test]
+    fn test_optimized_with_counterattack() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Catapult),
+            Unit::new(UnitType::Archer).with_current_hp(3),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(5),
+            Unit::new(UnitType::Archer),
+        ]);
+
+        // Left alone, the catapult takes the archer and the damaged warrior strikes back.
+        let (plain, log) = optimized(attackers.clone(), defenders.clone());
+        assert_eq!(plain, 113);
+        assert_eq!(log[0].attacker.unit_type, UnitType::Catapult);
+        assert_eq!(log[0].defender.unit_type, UnitType::Archer);
+
+        // Accounting for the counterattack, the archer goes first so the catapult can
+        // finish the enemy archer.
+        let (cautious, log) = optimized_with_counterattack(attackers, defenders);
+        assert_eq!(cautious, 98);
+        assert_eq!(log[0].attacker.unit_type, UnitType::Archer);
+        assert_eq!(log[1].attacker.unit_type, UnitType::Catapult);
+        assert_eq!(log[1].defender.unit_type, UnitType::Archer);
+        assert!(log[1].is_kill());
+    }
+
+    #[test]
+    fn test_veteran_kill() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Catapult).with_kills(1),
+            Unit::new(UnitType::Knight).with_kills(2),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(3),
+            Unit::new(UnitType::Warrior).with_current_hp(3),
+        ]);
+
+        let outcome = multi_combat_outcome(&attackers, defenders);
+
+        assert_eq!(outcome.log[0].kills, 2);
+        assert!(!outcome.log[0].earns_promotion());
+        assert_eq!(outcome.log[1].kills, 3);
+        assert!(outcome.log[1].earns_promotion());
+        assert!(outcome.attackers[1].can_promote());
+    }
+
+    #[test]
+    fn test_wa_vs_je_je_adjacent() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Warrior).with_position(Position::new(1, 1)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(1, 0)),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Jelly).with_position(Position::new(2, 1)),
+            Unit::new(UnitType::Jelly).with_position(Position::new(0, 0)),
+        ]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+        let kinds: Vec<_> = outcome.log.iter().map(|event| event.kind).collect();
+
+        // Neither warrior moves, so only the jelly each attacks strikes back.
+        assert_eq!(kinds, [EventKind::Attack, EventKind::Attack]);
+        assert_eq!(outcome.log.score(), score);
+    }
+
+    #[test]
+    fn test_kn_persists_next_to_je() {
+        let attackers =
+            Units::from([Unit::new(UnitType::Knight).with_position(Position::new(0, 0))]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior)
+                .with_current_hp(1)
+                .with_position(Position::new(1, 0)),
+            Unit::new(UnitType::Warrior)
+                .with_current_hp(1)
+                .with_position(Position::new(2, 0)),
+            Unit::new(UnitType::Jelly).with_position(Position::new(2, 1)),
+        ]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+        let kinds: Vec<_> = outcome.log.iter().map(|event| event.kind).collect();
+
+        // The jelly strikes when the knight persists next to it, but not again once the
+        // knight attacks it.
+        assert_eq!(
+            kinds,
+            [
+                EventKind::Attack,
+                EventKind::Tentacles,
+                EventKind::Attack,
+                EventKind::Attack
+            ]
+        );
+        assert_eq!(outcome.log[1].attacker.unit_type, UnitType::Jelly);
+        assert_eq!(outcome.log.score(), score);
+    }
+
+    #[test]
+    fn test_turn_start_skills() {
+        let jellies = Units::from([Unit::new(UnitType::Jelly).with_position(Position::new(1, 1))]);
+        let mut enemies = Units::from([
+            Unit::new(UnitType::Warrior).with_position(Position::new(2, 2)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(0, 1)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(3, 1)),
+        ]);
+
+        let log = turn_start_skills(&jellies, &mut enemies);
+
+        assert_eq!(log.len(), 2);
+        assert_eq!(enemies[0].current_hp, 5);
+        assert_eq!(enemies[1].current_hp, 5);
+        assert_eq!(enemies[2].current_hp, 10);
+    }
+
+    #[test]
+    fn test_ju_stomp() {
+        let attackers =
+            Units::from([Unit::new(UnitType::Juggernaut).with_position(Position::new(1, 1))]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_position(Position::new(2, 1)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(0, 0)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(3, 1)),
+        ]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+
+        assert_eq!(outcome.log[1].kind, EventKind::Stomp);
+        assert_eq!(outcome.log.len(), 2);
+        assert_eq!(outcome.log.score(), score);
+        assert_eq!(outcome.defenders[1].current_hp, 0);
+        assert_eq!(outcome.defenders[2].current_hp, 10);
+    }
+
+    #[test]
+    fn test_ju_stomp_kills() {
+        let attackers = Units::from([Unit::new(UnitType::Juggernaut)
+            .with_position(Position::new(1, 1))
+            .with_kills(1)]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior)
+                .with_current_hp(2)
+                .with_position(Position::new(2, 1)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(0, 0)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(1, 2)),
+        ]);
+
+        let outcome = multi_combat_outcome(&attackers, defenders);
+
+        let kills: Vec<_> = outcome.log.iter().map(|event| event.kills).collect();
+        assert_eq!(kills, [2, 3, 4]);
+        let promotions: Vec<_> = outcome
+            .log
+            .iter()
+            .map(|event| event.earns_promotion())
+            .collect();
+        assert_eq!(promotions, [false, true, false]);
+        assert_eq!(outcome.attackers[0].kills, 4);
+    }
+
+    #[test]
+    fn test_fd_splash() {
+        let attackers =
+            Units::from([Unit::new(UnitType::FireDragon).with_position(Position::new(0, 1))]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_position(Position::new(2, 1)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(3, 2)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(1, 1)),
+        ]);
+
+        let outcome = multi_combat_outcome(&attackers, defenders.clone());
+
+        let splash: Vec<_> = outcome
+            .log
+            .iter()
+            .filter(|event| event.kind == EventKind::Splash)
+            .map(|event| event.damage_to_defender)
+            .collect();
+        // Both other warriors stand next to the target and take half damage.
+        assert_eq!(splash.len(), 2);
+        assert!(
+            splash
+                .iter()
+                .all(|&damage| damage < outcome.log[0].damage_to_defender)
+        );
+        assert_eq!(
+            outcome.log.score(),
+            multi_combat_score(&attackers, defenders)
+        );
+    }
+
+    #[test]
+    fn test_kn_persist() {
+        let attackers = Units::from([Unit::new(UnitType::Knight)]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(2),
+            Unit::new(UnitType::Warrior).with_current_hp(2),
+            Unit::new(UnitType::Defender),
+        ]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+
+        assert_eq!(outcome.log.len(), 3);
+        assert_eq!(outcome.attackers[0].kills, 2);
+        assert_eq!(outcome.log.score(), score);
+    }
+
+    #[test]
+    fn test_mb_convert() {
+        let attackers = Units::from([Unit::new(UnitType::MindBender)]);
+        let defenders = Units::from([Unit::new(UnitType::Giant).with_current_hp(30)]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+
+        assert_eq!(outcome.converted.len(), 1);
+        assert_eq!(outcome.converted[0].current_hp, 30);
+        assert!(outcome.converted[0].status_effects.is_empty());
+        assert_eq!(outcome.defenders[0].current_hp, 0);
+        assert_eq!(outcome.log.score(), score);
+    }
+
+    #[test]
+    fn test_dx_explode() {
+        let doomux = Unit::new(UnitType::Doomux).with_position(Position::new(0, 1));
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_position(Position::new(1, 1)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(2, 1)),
+        ]);
+
+        let attacking = multi_combat_outcome(&Units::from([doomux.clone()]), defenders.clone());
+        let exploding = Units::from([doomux.with_status_effects(StatusEffects::EXPLODING)]);
+        let score = multi_combat_score(&exploding, defenders.clone());
+        let outcome = multi_combat_outcome(&exploding, defenders.clone());
+
+        assert_eq!(attacking.log.len(), 1);
+        assert!(attacking.attackers[0].current_hp > 0);
+        // The target takes half damage, just like the warrior next to it.
+        assert_eq!(outcome.log[1].kind, EventKind::Explosion);
+        assert_eq!(
+            outcome.log[0].damage_to_defender,
+            outcome.log[1].damage_to_defender
+        );
+        assert!(outcome.log[0].damage_to_defender < attacking.log[0].damage_to_defender);
+        assert_eq!(outcome.attackers[0].current_hp, 0);
+        assert_eq!(outcome.log.score(), score);
+
+        // Only units that can explode do.
+        let warrior = Unit::new(UnitType::Warrior)
+            .with_position(Position::new(0, 1))
+            .with_status_effects(StatusEffects::EXPLODING);
+        let outcome = multi_combat_outcome(&Units::from([warrior]), defenders);
+        assert_eq!(outcome.log.len(), 1);
+        assert!(outcome.attackers[0].current_hp > 0);
+    }
+
+    #[test]
+    fn test_ia_freeze() {
+        let (_, to_defender) = single_combat(
+            &Unit::new(UnitType::IceArcher),
+            &Unit::new(UnitType::Warrior),
+        );
+
+        assert_eq!(to_defender.status_effects, StatusEffects::FROZEN);
+    }
+
+    #[test]
+    fn test_mo_freeze_area() {
+        let moonis = Units::from([Unit::new(UnitType::Mooni).with_position(Position::new(1, 1))]);
+        let mut enemies = Units::from([
+            Unit::new(UnitType::Warrior).with_position(Position::new(2, 2)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(3, 1)),
+        ]);
+
+        let log = turn_start_skills(&moonis, &mut enemies);
+
+        assert_eq!(log.len(), 1);
+        assert_eq!(log.score(), 0);
+        assert!(enemies[0].status_effects.contains(StatusEffects::FROZEN));
+        assert!(!enemies[1].status_effects.contains(StatusEffects::FROZEN));
+    }
+
+    #[test]
+    fn test_ce_grows_on_kill() {
+        let attackers = Units::from([Unit::new(UnitType::Centipede)]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(5),
+            Unit::new(UnitType::Warrior),
+        ]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+
+        assert_eq!(outcome.log[1].kind, EventKind::Grow);
+        assert_eq!(outcome.attackers.len(), 2);
+        assert_eq!(outcome.attackers[1].unit_type, UnitType::Segment);
+        assert_eq!(outcome.attackers[1].body, outcome.attackers[0].body);
+        assert_eq!(outcome.acted, [true, false]);
+        assert_eq!(outcome.log.score(), score);
+    }
+
+    #[test]
+    fn test_ce_segments_die_with_head() {
+        let attackers = Units::from([Unit::new(UnitType::Giant)]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Centipede)
+                .with_current_hp(5)
+                .with_body(0),
+            Unit::new(UnitType::Segment).with_body(0),
+            Unit::new(UnitType::Segment).with_body(0),
+        ]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+
+        assert!(outcome.defenders.iter().all(|unit| unit.current_hp == 0));
+        assert_eq!(outcome.log.len(), 3);
+        assert_eq!(outcome.log[2].kills, 3);
+        assert_eq!(outcome.attackers[0].kills, 3);
+        assert_eq!(score, 5 + 100 + 2 * (10 + 100));
+        assert_eq!(outcome.log.score(), score);
+    }
+
+    #[test]
+    fn test_ce_attacking_head_dies() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Segment).with_body(0),
+            Unit::new(UnitType::Centipede)
+                .with_current_hp(1)
+                .with_body(0),
+            Unit::new(UnitType::Segment).with_body(0),
+        ]);
+        let defenders = Units::from([Unit::new(UnitType::Giant)]);
+
+        let score = multi_combat_score(&attackers, defenders.clone());
+        let outcome = multi_combat_outcome(&attackers, defenders);
+        let lost = outcome
+            .log
+            .iter()
+            .filter(|event| event.kind == EventKind::SegmentLost)
+            .count();
+
+        // The first segment already died to retaliation before the head attacked.
+        assert_eq!(lost, 1);
+        assert!(outcome.attackers.iter().all(|unit| unit.current_hp == 0));
+        assert!(!outcome.acted[2]);
+        assert_eq!(outcome.log.score(), score);
     }
 
     #[test]
@@ -496,7 +1692,7 @@ mod tests {
 
     #[test]
     fn test_b_sh_5_gi() {
-        let attacker = Unit::new(UnitType::Shaman).with_current_hp(5.0);
+        let attacker = Unit::new(UnitType::Shaman).with_current_hp(5);
         let defender = Unit::new(UnitType::Giant);
 
         let n_attacks = bulk(&attacker, defender);
@@ -504,6 +1700,53 @@ mod tests {
         assert_eq!(n_attacks, 35);
     }
 
+    #[test]
+    fn test_bulk_copies_wa_gi() {
+        let attacker = Unit::new(UnitType::Warrior);
+        let defender = Unit::new(UnitType::Giant);
+
+        let report = bulk_copies(&attacker, defender.clone()).unwrap();
+
+        assert!(report.killed);
+        assert_eq!(report.hits.len() as u32, bulk(&attacker, defender));
+        assert!(report.hits.iter().all(|hit| hit.attacker_hp == 10));
+        assert!(report.attackers_lost() > 0);
+        assert_eq!(
+            bulk_copies(&Unit::new(UnitType::Egg), Unit::new(UnitType::Giant)),
+            Err(EliminateError::NoAttack)
+        );
+    }
+
+    #[test]
+    fn test_bulk_copies_wa_gi_walled() {
+        let attacker = Unit::new(UnitType::Warrior).with_current_hp(1);
+        let defender = Unit::new(UnitType::Giant).with_status_effects(StatusEffects::WALLED);
+
+        // Full hp warriors chip through a point at a time, but a 1 hp one deals nothing.
+        assert!(bulk_copies(&Unit::new(UnitType::Warrior), defender.clone()).is_ok());
+        assert_eq!(
+            bulk_copies(&attacker, defender),
+            Err(EliminateError::TooStrong)
+        );
+    }
+
+    #[test]
+    fn test_bulk_repeated_kn_de() {
+        let attacker = Unit::new(UnitType::Knight);
+        let defender = Unit::new(UnitType::Defender);
+
+        let report = bulk_repeated(attacker, defender).unwrap();
+
+        // The knight wears down with every retaliation it takes.
+        assert!(report.hits.windows(2).all(|pair| {
+            pair[1].attacker_hp == pair[0].attacker_hp - pair[0].damage_received
+                && pair[1].defender_hp == pair[0].defender_hp - pair[0].damage_dealt
+        }));
+        let last = report.hits.last().unwrap();
+        assert_eq!(report.killed, last.is_kill());
+        assert!(report.killed || last.is_loss());
+    }
+
     #[test]
     fn test_e_wa_ri_u() {
         let attacker = Unit::new(UnitType::Warrior);
@@ -511,36 +1754,65 @@ mod tests {
 
         let max_hp = eliminate_defender(&attacker, defender);
 
-        assert_eq!(max_hp, 7.0);
+        assert_eq!(max_hp, Ok(7));
     }
 
     #[test]
     fn test_e_sh_1_gi_u() {
-        let attacker = Unit::new(UnitType::Shaman).with_current_hp(1.0);
+        let attacker = Unit::new(UnitType::Shaman).with_current_hp(1);
         let defender = Unit::new(UnitType::Giant);
 
+        // Shamans convert, so the giant is taken at any hp.
         let max_hp = eliminate_defender(&attacker, defender);
 
-        assert_eq!(max_hp, 2.0);
+        assert_eq!(max_hp, Ok(40));
+    }
+
+    #[test]
+    fn test_e_ar_1_gi_u() {
+        let attacker = Unit::new(UnitType::Archer).with_current_hp(1);
+        let defender = Unit::new(UnitType::Giant);
+
+        let max_hp = eliminate_defender(&attacker, defender);
+
+        assert_eq!(max_hp, Ok(3));
+    }
+
+    #[test]
+    fn test_e_never_possible() {
+        let walled = Unit::new(UnitType::Giant).with_status_effects(StatusEffects::WALLED);
+
+        assert_eq!(
+            eliminate_defender(&Unit::new(UnitType::Egg), walled.clone()),
+            Err(EliminateError::NoAttack)
+        );
+        assert_eq!(
+            eliminate_attacker(Unit::new(UnitType::Warrior), &walled),
+            Err(EliminateError::TooStrong)
+        );
+        assert_eq!(
+            eliminate_attacker(Unit::new(UnitType::MindBender), &walled),
+            Ok(1)
+        );
     }
 
     #[test]
     fn test_e_wa_u_ri_4() {
         let attacker = Unit::new(UnitType::Warrior);
-        let defender = Unit::new(UnitType::Rider).with_current_hp(4.0);
+        let defender = Unit::new(UnitType::Rider).with_current_hp(4);
 
         let min_hp = eliminate_attacker(attacker, &defender);
 
-        assert_eq!(min_hp, 2.0);
+        assert_eq!(min_hp, Ok(2));
     }
 
     #[test]
     fn test_e_gi_u_gi_15() {
         let attacker = Unit::new(UnitType::Giant);
-        let defender = Unit::new(UnitType::Giant).with_current_hp(15.0);
+        let defender = Unit::new(UnitType::Giant).with_current_hp(15);
 
         let min_hp = eliminate_attacker(attacker, &defender);
 
-        assert_eq!(min_hp, 22.0);
+        assert_eq!(min_hp, Ok(22));
     }
 }
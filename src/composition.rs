@@ -0,0 +1,253 @@
+use std::fmt;
+
+use crate::{
+    combat::{CombatLog, multi_combat_outcome, optimized_order},
+    unit::{MAX_UNITS, Stars, Unit, UnitType, Units},
+};
+
+/// The most volleys [`cheapest_composition`] may simulate, counting every order of every
+/// army it could try.
+pub const MAX_VOLLEYS: u64 = 10_000_000;
+
+/// What [`cheapest_composition`] may buy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositionOptions {
+    /// The unit types to choose from. Each can be bought any number of times.
+    pub available: Vec<UnitType>,
+    /// The most stars the whole army may cost.
+    pub budget: Stars,
+    /// The most units in the army, capped at [`MAX_UNITS`].
+    pub max_count: usize,
+}
+
+/// An army that kills every defender, with its attacks.
+#[derive(Debug)]
+pub struct Composition {
+    /// The attackers, in the order they attack.
+    pub attackers: Units,
+    pub cost: Stars,
+    pub log: CombatLog,
+}
+
+/// Why [`cheapest_composition`] found no army.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositionError {
+    /// No army within the budget and count kills every defender.
+    NoArmy,
+    /// The search could take more than [`MAX_VOLLEYS`] volleys.
+    TooLarge,
+}
+
+impl fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoArmy => write!(f, "no army within the budget kills every defender"),
+            Self::TooLarge => {
+                write!(f, "the search could take more than {MAX_VOLLEYS} volleys")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompositionError {}
+
+/// The volleys a search over armies of up to `max_count` units of `n_types` types could
+/// simulate against `n_defenders` defenders, saturating at `u64::MAX`.
+///
+/// There are `n_types * (n_types + 1) * .. * (n_types + k - 1) / k!` armies of `k` units,
+/// and each is tried in `k! * n_defenders!` orders.
+fn worst_case_volleys(n_types: usize, max_count: usize, n_defenders: usize) -> u64 {
+    let defender_orders = (1..=n_defenders as u64).fold(1u64, u64::saturating_mul);
+    let mut armies_in_order = 1u64;
+    let mut volleys = 0u64;
+    for k in 0..max_count {
+        armies_in_order = armies_in_order.saturating_mul((n_types + k) as u64);
+        volleys = volleys.saturating_add(armies_in_order.saturating_mul(defender_orders));
+    }
+    volleys
+}
+
+/// Whether `attackers` kill every defender in the orders [`optimized_order`] picks. Returns
+/// the attacker order and its log if they do.
+fn kills_all(attackers: Units, defenders: &Units) -> Option<(Units, CombatLog)> {
+    let (_, attackers, defenders) = optimized_order(attackers, defenders.clone());
+    let outcome = multi_combat_outcome(&attackers, defenders);
+
+    outcome
+        .defenders
+        .iter()
+        .all(|unit| unit.current_hp == 0)
+        .then_some((attackers, outcome.log))
+}
+
+/// Tries every multiset of `types[from..]` that fits in the remaining budget and count,
+/// added to `army`, keeping the cheapest one that kills every defender in `best`.
+fn search(
+    types: &[UnitType],
+    from: usize,
+    army: &mut Vec<UnitType>,
+    cost: Stars,
+    options: &CompositionOptions,
+    defenders: &Units,
+    best: &mut Option<Composition>,
+) {
+    if !army.is_empty() {
+        let attackers: Units = army.iter().map(|&unit_type| Unit::new(unit_type)).collect();
+        if let Some((attackers, log)) = kills_all(attackers, defenders) {
+            *best = Some(Composition {
+                attackers,
+                cost,
+                log,
+            });
+            // Adding units only costs more.
+            return;
+        }
+    }
+    if army.len() >= options.max_count.min(MAX_UNITS) {
+        return;
+    }
+
+    for (i, &unit_type) in types.iter().enumerate().skip(from) {
+        let cost = cost + unit_type.cost();
+        // Only strictly cheaper armies can replace the best one.
+        if cost > options.budget || best.as_ref().is_some_and(|best| cost >= best.cost) {
+            continue;
+        }
+
+        army.push(unit_type);
+        search(types, i, army, cost, options, defenders, best);
+        army.pop();
+    }
+}
+
+/// Finds the cheapest army of `options.available` unit types that kills every defender,
+/// with each army attacking in the orders [`optimized_order`] picks. An army whose
+/// best-scoring orders leave a defender standing is passed over, even if other orders
+/// would kill them all.
+///
+/// Among armies of the same cost, the first found wins. Fails without searching if the
+/// search could take more than [`MAX_VOLLEYS`] volleys.
+pub fn cheapest_composition(
+    defenders: &Units,
+    options: &CompositionOptions,
+) -> Result<Composition, CompositionError> {
+    if defenders.is_empty() {
+        return Ok(Composition {
+            attackers: Units::new(),
+            cost: 0,
+            log: CombatLog::new(),
+        });
+    }
+
+    // Trying cheap units first finds a good bound early.
+    let mut types = options.available.clone();
+    types.sort_by_key(|&unit_type| (unit_type.cost(), unit_type as usize));
+    types.dedup();
+
+    let max_count = options.max_count.min(MAX_UNITS);
+    if worst_case_volleys(types.len(), max_count, defenders.len()) > MAX_VOLLEYS {
+        return Err(CompositionError::TooLarge);
+    }
+
+    let mut best = None;
+    search(&types, 0, &mut Vec::new(), 0, options, defenders, &mut best);
+    best.ok_or(CompositionError::NoArmy)
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::unit::StatusEffects;
+
+    use super::*;
+
+    /// The cheapest cost of any army in `options` that kills every defender, trying every
+    /// army without pruning.
+    fn brute_force_cost(defenders: &Units, options: &CompositionOptions) -> Option<Stars> {
+        (1..=options.max_count)
+            .flat_map(|count| {
+                options
+                    .available
+                    .iter()
+                    .copied()
+                    .combinations_with_replacement(count)
+            })
+            .filter_map(|army| {
+                let cost = army.iter().map(|unit_type| unit_type.cost()).sum();
+                let attackers = army.into_iter().map(Unit::new).collect();
+                (cost <= options.budget && kills_all(attackers, defenders).is_some())
+                    .then_some(cost)
+            })
+            .min()
+    }
+
+    #[test]
+    fn test_cheapest_vs_fortified_swordsmen() {
+        let defenders = Units::from([
+            Unit::new(UnitType::Swordsman).with_status_effects(StatusEffects::FORTIFIED),
+            Unit::new(UnitType::Swordsman).with_status_effects(StatusEffects::FORTIFIED),
+        ]);
+        let options = CompositionOptions {
+            available: vec![
+                UnitType::Warrior,
+                UnitType::Archer,
+                UnitType::Catapult,
+                UnitType::Knight,
+            ],
+            budget: 40,
+            max_count: 5,
+        };
+
+        let composition = cheapest_composition(&defenders, &options).unwrap();
+
+        let cost: Stars = composition.attackers.iter().map(Unit::cost).sum();
+        assert_eq!(composition.cost, cost);
+        assert_eq!(composition.cost, 16);
+        assert_eq!(
+            Some(composition.cost),
+            brute_force_cost(&defenders, &options)
+        );
+        assert_eq!(
+            composition
+                .log
+                .iter()
+                .filter(|event| event.is_kill())
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_no_composition_in_budget() {
+        let defenders = Units::from([Unit::new(UnitType::Giant)]);
+        let options = CompositionOptions {
+            available: vec![UnitType::Warrior],
+            budget: 6,
+            max_count: 8,
+        };
+
+        assert_eq!(
+            cheapest_composition(&defenders, &options).unwrap_err(),
+            CompositionError::NoArmy
+        );
+    }
+
+    #[test]
+    fn test_search_too_large() {
+        let defenders: Units = std::iter::repeat_with(|| Unit::new(UnitType::Warrior))
+            .take(6)
+            .collect();
+        let options = CompositionOptions {
+            available: vec![UnitType::Warrior, UnitType::Archer, UnitType::Knight],
+            budget: 100,
+            max_count: MAX_UNITS,
+        };
+
+        assert_eq!(
+            cheapest_composition(&defenders, &options).unwrap_err(),
+            CompositionError::TooLarge
+        );
+        assert_eq!(worst_case_volleys(3, 2, 2), (3 + 3 * 4) * 2);
+    }
+}
@@ -0,0 +1,369 @@
+use std::fmt;
+
+use arrayvec::ArrayVec;
+
+use crate::{
+    combat::{CombatLog, multi_combat_outcome_sparing, multi_combat_score_sparing},
+    unit::{MAX_UNITS, Units},
+    utils::Perms,
+};
+
+/// Requirements an attack plan must meet. Units are referred to by their index in the
+/// attackers and defenders given to [`optimized_constrained`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Constraints {
+    /// Attackers that must attack at a fixed place in the order, as `(attacker, place)`.
+    pub pinned: Vec<(usize, usize)>,
+    /// Attackers that must survive the volley.
+    pub survivors: Vec<usize>,
+    /// Defenders that must not be attacked. No attacker picks them as its target, but they
+    /// stay on the board, so their tentacles still strike and area damage still hits them.
+    pub forbidden: Vec<usize>,
+    /// Defenders that must be killed or converted.
+    pub must_kill: Vec<usize>,
+}
+
+/// Why no plan can be found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintError {
+    /// A constraint refers to a unit or place that does not exist.
+    OutOfRange,
+    /// Two attackers are pinned to the same place, or one attacker to two places.
+    ConflictingPins,
+    /// No order meets every constraint.
+    Unsatisfiable,
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(f, "a constraint refers to a unit that does not exist"),
+            Self::ConflictingPins => write!(f, "the pinned attackers conflict"),
+            Self::Unsatisfiable => write!(f, "no attack order meets every constraint"),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintError {}
+
+impl Constraints {
+    fn validate(&self, n_attackers: usize, n_defenders: usize) -> Result<(), ConstraintError> {
+        let attackers_ok = self
+            .pinned
+            .iter()
+            .all(|&(attacker, place)| attacker < n_attackers && place < n_attackers)
+            && self
+                .survivors
+                .iter()
+                .all(|&attacker| attacker < n_attackers);
+        let defenders_ok = self
+            .forbidden
+            .iter()
+            .chain(&self.must_kill)
+            .all(|&defender| defender < n_defenders);
+        if !attackers_ok || !defenders_ok {
+            return Err(ConstraintError::OutOfRange);
+        }
+
+        for (i, &(attacker, place)) in self.pinned.iter().enumerate() {
+            let conflicts = self.pinned[i + 1..]
+                .iter()
+                .any(|&(other, other_place)| (other == attacker) != (other_place == place));
+            if conflicts {
+                return Err(ConstraintError::ConflictingPins);
+            }
+        }
+        if self.forbidden.iter().any(|d| self.must_kill.contains(d)) {
+            return Err(ConstraintError::Unsatisfiable);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the attackers in the order given by `attacker_order` meet the pins.
+    fn pins_hold(&self, attacker_order: &[usize]) -> bool {
+        self.pinned
+            .iter()
+            .all(|&(attacker, place)| attacker_order[place] == attacker)
+    }
+
+    /// The places of the forbidden defenders in the order given by `defender_order`.
+    fn spared(&self, defender_order: &[usize]) -> ArrayVec<usize, MAX_UNITS> {
+        defender_order
+            .iter()
+            .enumerate()
+            .filter(|(_, defender)| self.forbidden.contains(defender))
+            .map(|(place, _)| place)
+            .collect()
+    }
+
+    /// Whether the volley of `attackers` on `defenders`, which are the original units put
+    /// in the given orders, keeps the survivors alive and kills the must-kill defenders.
+    fn outcome_holds(
+        &self,
+        attackers: &Units,
+        attacker_order: &[usize],
+        defenders: &Units,
+        defender_order: &[usize],
+        spared: &[usize],
+    ) -> bool {
+        let outcome = multi_combat_outcome_sparing(attackers, defenders.clone(), spared);
+        let place_of = |order: &[usize], unit| order.iter().position(|&u| u == unit).unwrap();
+
+        self.survivors
+            .iter()
+            .all(|&attacker| outcome.attackers[place_of(attacker_order, attacker)].current_hp > 0)
+            && self.must_kill.iter().all(|&defender| {
+                outcome.defenders[place_of(defender_order, defender)].current_hp == 0
+            })
+    }
+}
+
+/// Like [`optimized`](crate::combat::optimized), but only considers orders that meet
+/// `constraints`.
+pub fn optimized_constrained(
+    attackers: Units,
+    defenders: Units,
+    constraints: &Constraints,
+) -> Result<(i32, CombatLog), ConstraintError> {
+    optimized_constrained_by(
+        attackers,
+        defenders,
+        constraints,
+        multi_combat_score_sparing,
+    )
+}
+
+/// Like [`optimized_constrained`], but ranks orders with `score` instead of
+/// [`multi_combat_score_sparing`].
+///
+/// `score` is called like [`multi_combat_score_sparing`], with the places of the forbidden
+/// defenders in the order.
+pub fn optimized_constrained_by(
+    mut attackers: Units,
+    mut defenders: Units,
+    constraints: &Constraints,
+    mut score: impl FnMut(&Units, Units, &[usize]) -> i32,
+) -> Result<(i32, CombatLog), ConstraintError> {
+    constraints.validate(attackers.len(), defenders.len())?;
+
+    // Track where each original unit ends up as the orders are permuted.
+    let mut attacker_order: Vec<usize> = (0..attackers.len()).collect();
+    let mut defender_order: Vec<usize> = (0..defenders.len()).collect();
+
+    let mut best: Option<(i32, Units, Units, ArrayVec<usize, MAX_UNITS>)> = None;
+    let mut consider = |attackers: &Units,
+                        attacker_order: &[usize],
+                        defenders: &Units,
+                        defender_order: &[usize]| {
+        let spared = constraints.spared(defender_order);
+        if !constraints.pins_hold(attacker_order)
+            || !constraints.outcome_holds(
+                attackers,
+                attacker_order,
+                defenders,
+                defender_order,
+                &spared,
+            )
+        {
+            return;
+        }
+
+        let score = score(attackers, defenders.clone(), &spared);
+        if best
+            .as_ref()
+            .is_none_or(|(top_score, _, _, _)| score > *top_score)
+        {
+            best = Some((score, attackers.clone(), defenders.clone(), spared));
+        }
+    };
+
+    if attackers.is_empty() || defenders.is_empty() {
+        consider(&attackers, &attacker_order, &defenders, &defender_order);
+    } else {
+        for (a_first, a_second) in Perms::new(attackers.len()) {
+            attackers.swap(a_first, a_second);
+            attacker_order.swap(a_first, a_second);
+
+            for (d_first, d_second) in Perms::new(defenders.len()) {
+                defenders.swap(d_first, d_second);
+                defender_order.swap(d_first, d_second);
+                consider(&attackers, &attacker_order, &defenders, &defender_order);
+            }
+        }
+    }
+
+    let (top_score, attackers, defenders, spared) = best.ok_or(ConstraintError::Unsatisfiable)?;
+    Ok((
+        top_score,
+        multi_combat_outcome_sparing(&attackers, defenders, &spared).log,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Position,
+        combat::{EventKind, optimized},
+        unit::{Unit, UnitType},
+    };
+
+    use super::*;
+
+    fn fight() -> (Units, Units) {
+        let attackers = Units::from([
+            Unit::new(UnitType::Warrior),
+            Unit::new(UnitType::Catapult),
+            Unit::new(UnitType::Knight),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(4),
+            Unit::new(UnitType::Archer),
+        ]);
+        (attackers, defenders)
+    }
+
+    #[test]
+    fn test_pinned_and_forbidden() {
+        let (attackers, defenders) = fight();
+        let constraints = Constraints {
+            pinned: vec![(1, 0)],
+            forbidden: vec![1],
+            ..Default::default()
+        };
+
+        let (_, log) = optimized_constrained(attackers, defenders, &constraints).unwrap();
+
+        assert_eq!(log[0].attacker.unit_type, UnitType::Catapult);
+        assert!(
+            log.iter()
+                .all(|event| event.defender.unit_type != UnitType::Archer)
+        );
+    }
+
+    #[test]
+    fn test_forbidden_jelly_still_strikes() {
+        let attackers =
+            Units::from([Unit::new(UnitType::Knight).with_position(Position::new(0, 0))]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Jelly).with_position(Position::new(2, 1)),
+            Unit::new(UnitType::Warrior)
+                .with_current_hp(1)
+                .with_position(Position::new(1, 0)),
+            Unit::new(UnitType::Warrior)
+                .with_current_hp(1)
+                .with_position(Position::new(2, 0)),
+        ]);
+        let constraints = Constraints {
+            forbidden: vec![0],
+            ..Default::default()
+        };
+
+        let (score, log) = optimized_constrained(attackers, defenders, &constraints).unwrap();
+
+        // The knight persists past the jelly without attacking it, and is struck every
+        // time it moves next to it.
+        let kinds: Vec<_> = log.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                EventKind::Attack,
+                EventKind::Tentacles,
+                EventKind::Attack,
+                EventKind::Tentacles
+            ]
+        );
+        assert!(
+            log.iter()
+                .filter(|event| event.kind == EventKind::Attack)
+                .all(|event| event.defender.unit_type == UnitType::Warrior)
+        );
+        assert_eq!(score, log.score());
+    }
+
+    #[test]
+    fn test_survivors() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Catapult).with_current_hp(2),
+            Unit::new(UnitType::Warrior),
+            Unit::new(UnitType::Warrior).with_current_hp(3),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(8),
+            Unit::new(UnitType::Archer).with_current_hp(8),
+        ]);
+        let constraints = Constraints {
+            survivors: vec![2],
+            ..Default::default()
+        };
+
+        let (plain, _) = optimized(attackers.clone(), defenders.clone());
+        let (score, log) = optimized_constrained(attackers, defenders, &constraints).unwrap();
+
+        // The best plan sends the hurt warrior to its death, so the fresh one goes first.
+        assert_eq!((plain, score), (213, 212));
+        assert!(
+            log.iter()
+                .all(|event| event.damage_to_attacker < event.attacker.current_hp)
+        );
+        assert_eq!(log[2].attacker.current_hp, 3);
+    }
+
+    #[test]
+    fn test_must_kill() {
+        let attackers = Units::from([Unit::new(UnitType::Warrior), Unit::new(UnitType::Warrior)]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(8),
+            Unit::new(UnitType::Archer).with_current_hp(8),
+        ]);
+        let constraints = Constraints {
+            must_kill: vec![0],
+            ..Default::default()
+        };
+
+        let (plain, _) = optimized(attackers.clone(), defenders.clone());
+        let (score, log) = optimized_constrained(attackers, defenders, &constraints).unwrap();
+
+        // Killing the archer scores best, but the warrior has to go.
+        assert_eq!((plain, score), (107, 104));
+        let kills: Vec<_> = log
+            .iter()
+            .filter(|event| event.is_kill())
+            .map(|event| event.defender.unit_type)
+            .collect();
+        assert_eq!(kills, [UnitType::Warrior]);
+    }
+
+    #[test]
+    fn test_unsatisfiable() {
+        let (attackers, defenders) = fight();
+
+        let giant = Units::from([Unit::new(UnitType::Giant)]);
+        let must_kill = Constraints {
+            must_kill: vec![0],
+            ..Default::default()
+        };
+        assert_eq!(
+            optimized_constrained(attackers.clone(), giant, &must_kill).unwrap_err(),
+            ConstraintError::Unsatisfiable
+        );
+
+        let conflicting = Constraints {
+            pinned: vec![(0, 0), (1, 0)],
+            ..Default::default()
+        };
+        assert_eq!(
+            optimized_constrained(attackers.clone(), defenders.clone(), &conflicting).unwrap_err(),
+            ConstraintError::ConflictingPins
+        );
+
+        let out_of_range = Constraints {
+            survivors: vec![3],
+            ..Default::default()
+        };
+        assert_eq!(
+            optimized_constrained(attackers, defenders, &out_of_range).unwrap_err(),
+            ConstraintError::OutOfRange
+        );
+    }
+}
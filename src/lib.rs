@@ -1,3 +1,13 @@
+pub mod battlefield;
+pub mod board;
 pub mod combat;
+pub mod composition;
+pub mod constraints;
+pub mod matrix;
+pub mod pareto;
+pub mod planner;
+pub mod robust;
+pub mod siege;
+pub mod trade;
 pub mod unit;
 pub mod utils;
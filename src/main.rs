@@ -4,20 +4,10 @@ use polycalculato_rs::combat::{multi_combat_log, multi_combat_score, optimized};
 use polycalculato_rs::unit::{StatusEffects, Unit, UnitType, Units};
 use utils::repr_optim;
 
-mod utils {
-    use polycalculato_rs::{combat::CombatLog, unit::StatusEffects};
-
-    fn status_effects_to_str(status_effects: StatusEffects) -> String {
-        let mut out = String::new();
-        for status_effect in status_effects.iter_names() {
-            if !out.is_empty() {
-                out.push_str(", ");
-            }
-            out.push_str(status_effect.0);
-        }
+mod serve;
 
-        out
-    }
+mod utils {
+    use polycalculato_rs::combat::{CombatLog, EventKind};
 
     pub fn repr_optim(log: CombatLog) -> String {
         let mut out = String::new();
@@ -25,44 +15,43 @@ mod utils {
         out.push_str("This is the order for the best outcome:\n\n");
         out.push_str("**Attacker (statusEffects): startHP ➔ endHP**\n");
 
-        for event in log.iter() {
+        for event in log.iter().filter(|event| event.kind == EventKind::Attack) {
             out.push_str(event.attacker.unit_type.into());
             if event.status_effects_to_attacker.is_empty() {
                 out.push_str(": ");
             } else {
-                out.push_str(&format!(
-                    " ({}): ",
-                    status_effects_to_str(event.status_effects_to_attacker)
-                ));
+                out.push_str(&format!(" ({}): ", event.status_effects_to_attacker));
             }
-            out.push_str(&(event.attacker.current_hp.round() as i32).to_string());
+            out.push_str(&event.attacker.current_hp.to_string());
             out.push_str(" ➔  ");
             out.push_str(
-                &((event.attacker.current_hp - event.damage_to_attacker)
-                    .max(0.0)
-                    .round() as i32)
+                &event
+                    .attacker
+                    .current_hp
+                    .saturating_sub(event.damage_to_attacker)
                     .to_string(),
             );
+            if event.earns_promotion() {
+                out.push_str(" (veteran kill)");
+            }
             out.push('\n');
         }
 
         out.push_str("\n**Defender (statusEffects): startHP ➔ endHP**\n");
-        for event in log.iter() {
+        for event in log.iter().filter(|event| event.kind == EventKind::Attack) {
             out.push_str(event.defender.unit_type.into());
             if event.status_effects_to_defender.is_empty() {
                 out.push_str(": ");
             } else {
-                out.push_str(&format!(
-                    " ({}): ",
-                    status_effects_to_str(event.status_effects_to_attacker)
-                ));
+                out.push_str(&format!(" ({}): ", event.status_effects_to_defender));
             }
-            out.push_str(&(event.defender.current_hp.round() as i32).to_string());
+            out.push_str(&event.defender.current_hp.to_string());
             out.push_str(" ➔  ");
             out.push_str(
-                &((event.defender.current_hp - event.damage_to_defender)
-                    .max(0.0)
-                    .round() as i32)
+                &event
+                    .defender
+                    .current_hp
+                    .saturating_sub(event.damage_to_defender)
                     .to_string(),
             );
             out.push('\n');
@@ -72,21 +61,143 @@ mod utils {
     }
 }
 
+mod cli {
+    use polycalculato_rs::matrix::{Format, MatrixOptions, damage_matrix, export, hp_sensitivity};
+    use polycalculato_rs::unit::{StatusEffects, Unit, UnitType};
+
+    /// A subcommand, given the arguments after its name and returning what to print.
+    pub type Command = fn(&[String]) -> Result<String, String>;
+
+    fn effects(value: &str) -> Result<StatusEffects, String> {
+        if value.eq_ignore_ascii_case("none") {
+            return Ok(StatusEffects::empty());
+        }
+        value.parse().map_err(|err| format!("{err}"))
+    }
+
+    /// `matrix [--format csv|md|json] [--tribe T] [--attacker-tribe T] [--defender-tribe T]
+    /// [--attacker-hp N] [--defender-hp N] [--attacker-effects E]... [--defender-effects E]...`
+    ///
+    /// Status effects use the usual names or shorthand letters, or `none`. Giving them
+    /// replaces the default combos, and each flag adds one combo.
+    pub fn matrix(args: &[String]) -> Result<String, String> {
+        let mut options = MatrixOptions::default();
+        let mut format = Format::Csv;
+        let mut attacker_effects = Vec::new();
+        let mut defender_effects = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for `{flag}`"))?;
+            match flag.as_str() {
+                "--format" => format = value.parse().map_err(|err| format!("{err}"))?,
+                "--tribe" => {
+                    let tribe = value.parse().map_err(|err| format!("{err}"))?;
+                    options.attacker_tribe = Some(tribe);
+                    options.defender_tribe = Some(tribe);
+                }
+                "--attacker-tribe" => {
+                    options.attacker_tribe = Some(value.parse().map_err(|err| format!("{err}"))?)
+                }
+                "--defender-tribe" => {
+                    options.defender_tribe = Some(value.parse().map_err(|err| format!("{err}"))?)
+                }
+                "--attacker-hp" => {
+                    options.attacker_hp = Some(value.parse().map_err(|err| format!("{err}"))?)
+                }
+                "--defender-hp" => {
+                    options.defender_hp = Some(value.parse().map_err(|err| format!("{err}"))?)
+                }
+                "--attacker-effects" => attacker_effects.push(effects(value)?),
+                "--defender-effects" => defender_effects.push(effects(value)?),
+                _ => return Err(format!("unknown flag `{flag}`")),
+            }
+        }
+
+        if !attacker_effects.is_empty() {
+            options.attacker_effects = attacker_effects;
+        }
+        if !defender_effects.is_empty() {
+            options.defender_effects = defender_effects;
+        }
+
+        Ok(export(&damage_matrix(&options), format))
+    }
+
+    /// `sensitivity <attacker> <defender> [--format grid|csv|md|json] [--attacker-effects E]
+    /// [--defender-effects E]`
+    pub fn sensitivity(args: &[String]) -> Result<String, String> {
+        let [attacker, defender, flags @ ..] = args else {
+            return Err("expected an attacker and a defender".to_owned());
+        };
+        let attacker: UnitType = attacker.parse().map_err(|err| format!("{err}"))?;
+        let defender: UnitType = defender.parse().map_err(|err| format!("{err}"))?;
+
+        let mut format = None;
+        let mut attacker_effects = StatusEffects::empty();
+        let mut defender_effects = StatusEffects::empty();
+
+        let mut flags = flags.iter();
+        while let Some(flag) = flags.next() {
+            let value = flags
+                .next()
+                .ok_or_else(|| format!("missing value for `{flag}`"))?;
+            match flag.as_str() {
+                "--format" if value == "grid" => format = None,
+                "--format" => format = Some(value.parse().map_err(|err| format!("{err}"))?),
+                "--attacker-effects" => attacker_effects = effects(value)?,
+                "--defender-effects" => defender_effects = effects(value)?,
+                _ => return Err(format!("unknown flag `{flag}`")),
+            }
+        }
+
+        let table = hp_sensitivity(
+            &Unit::new(attacker).with_status_effects(attacker_effects),
+            &Unit::new(defender).with_status_effects(defender_effects),
+        );
+
+        Ok(match format {
+            Some(format) => table.export(format),
+            None => table.to_string(),
+        })
+    }
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command: Option<cli::Command> = match args.first().map(String::as_str) {
+        Some("matrix") => Some(cli::matrix),
+        Some("sensitivity") => Some(cli::sensitivity),
+        Some("serve") => Some(serve::serve),
+        _ => None,
+    };
+    if let Some(command) = command {
+        match command(&args[1..]) {
+            Ok(out) => print!("{out}"),
+            Err(err) => {
+                eprintln!("error: {err}");
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
     let attackers = Units::from([
-        Unit::new(UnitType::Rider).with_current_hp(6.0),
+        Unit::new(UnitType::Rider).with_current_hp(6),
         Unit::new(UnitType::Warrior),
         Unit::new(UnitType::Warrior),
         Unit::new(UnitType::Warrior),
         Unit::new(UnitType::Knight),
         Unit::new(UnitType::Catapult),
-        Unit::new(UnitType::Archer).with_current_hp(3.0),
+        Unit::new(UnitType::Archer).with_current_hp(3),
         Unit::new(UnitType::Catapult)
-            .with_current_hp(5.0)
+            .with_current_hp(5)
             .with_status_effects(StatusEffects::VETERAN),
-        Unit::new(UnitType::Archer).with_current_hp(3.0),
+        Unit::new(UnitType::Archer).with_current_hp(3),
         Unit::new(UnitType::Catapult)
-            .with_current_hp(5.0)
+            .with_current_hp(5)
             .with_status_effects(StatusEffects::VETERAN),
     ]);
     let defenders = Units::from([
@@ -0,0 +1,470 @@
+use std::fmt;
+use std::str::FromStr;
+
+use strum::VariantArray;
+
+use crate::{
+    combat::single_combat,
+    unit::{Hp, StatusEffects, Tribe, Unit, UnitType},
+};
+
+/// Which units and status effects [`damage_matrix`] pits against each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixOptions {
+    /// Only attackers this tribe can field, see [`Tribe::fields`].
+    pub attacker_tribe: Option<Tribe>,
+    /// Only defenders this tribe can field, see [`Tribe::fields`].
+    pub defender_tribe: Option<Tribe>,
+    /// Hp of every attacker, capped at its max hp. Attackers are at full hp if unset.
+    pub attacker_hp: Option<Hp>,
+    /// Hp of every defender, capped at its max hp. Defenders are at full hp if unset.
+    pub defender_hp: Option<Hp>,
+    /// Every attacker is tried with each of these.
+    pub attacker_effects: Vec<StatusEffects>,
+    /// Every defender is tried with each of these.
+    pub defender_effects: Vec<StatusEffects>,
+}
+
+impl Default for MatrixOptions {
+    fn default() -> Self {
+        Self {
+            attacker_tribe: None,
+            defender_tribe: None,
+            attacker_hp: None,
+            defender_hp: None,
+            attacker_effects: vec![StatusEffects::empty()],
+            defender_effects: vec![
+                StatusEffects::empty(),
+                StatusEffects::FORTIFIED,
+                StatusEffects::WALLED,
+            ],
+        }
+    }
+}
+
+/// One attack of the matrix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixEntry {
+    pub attacker: UnitType,
+    pub attacker_effects: StatusEffects,
+    pub attacker_hp: Hp,
+    pub defender: UnitType,
+    pub defender_effects: StatusEffects,
+    pub defender_hp: Hp,
+    pub damage_dealt: Hp,
+    /// Retaliation and tentacle damage the attacker takes.
+    pub damage_received: Hp,
+}
+
+impl MatrixEntry {
+    pub fn is_kill(&self) -> bool {
+        self.damage_dealt >= self.defender_hp
+    }
+}
+
+fn prepare(unit_type: UnitType, effects: StatusEffects, hp: Option<Hp>) -> Unit {
+    let unit = Unit::new(unit_type).with_status_effects(effects);
+    let hp = hp.map_or(unit.max_hp, |hp| hp.min(unit.max_hp));
+    unit.with_current_hp(hp)
+}
+
+fn in_tribe(unit_type: UnitType, tribe: Option<Tribe>) -> bool {
+    tribe.is_none_or(|tribe| tribe.fields(unit_type.tribe()))
+}
+
+/// Runs every pair of unit types through [`single_combat`], once for every combination of
+/// `options.attacker_effects` and `options.defender_effects`.
+///
+/// Units without attack never attack, but are still attacked.
+pub fn damage_matrix(options: &MatrixOptions) -> Vec<MatrixEntry> {
+    let mut entries = Vec::new();
+
+    for &attacker_type in UnitType::VARIANTS {
+        if !in_tribe(attacker_type, options.attacker_tribe) || Unit::new(attacker_type).attack == 0
+        {
+            continue;
+        }
+
+        for &attacker_effects in &options.attacker_effects {
+            let attacker = prepare(attacker_type, attacker_effects, options.attacker_hp);
+
+            for &defender_type in UnitType::VARIANTS {
+                if !in_tribe(defender_type, options.defender_tribe) {
+                    continue;
+                }
+
+                for &defender_effects in &options.defender_effects {
+                    let defender = prepare(defender_type, defender_effects, options.defender_hp);
+                    let (to_attacker, to_defender) = single_combat(&attacker, &defender);
+
+                    entries.push(MatrixEntry {
+                        attacker: attacker_type,
+                        attacker_effects,
+                        attacker_hp: attacker.current_hp,
+                        defender: defender_type,
+                        defender_effects,
+                        defender_hp: defender.current_hp,
+                        damage_dealt: to_defender.damage,
+                        damage_received: to_attacker.damage,
+                    });
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/// A file format [`export`] can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Markdown,
+    Json,
+}
+
+/// The error returned when a string is not the name of a [`Format`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFormatError {
+    input: String,
+}
+
+impl fmt::Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown format `{}`, expected csv, markdown or json",
+            self.input
+        )
+    }
+}
+
+impl std::error::Error for ParseFormatError {}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    /// Parses `csv`, `markdown` (or `md`) or `json`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "json" => Ok(Format::Json),
+            _ => Err(ParseFormatError {
+                input: s.to_owned(),
+            }),
+        }
+    }
+}
+
+const COLUMNS: [&str; 9] = [
+    "attacker",
+    "attacker_effects",
+    "attacker_hp",
+    "defender",
+    "defender_effects",
+    "defender_hp",
+    "damage_dealt",
+    "damage_received",
+    "kill",
+];
+
+/// The cells of an entry in [`COLUMNS`] order. Status effects use their shorthand.
+fn cells(entry: &MatrixEntry) -> Vec<String> {
+    vec![
+        entry.attacker.to_string(),
+        format!("{:#}", entry.attacker_effects),
+        entry.attacker_hp.to_string(),
+        entry.defender.to_string(),
+        format!("{:#}", entry.defender_effects),
+        entry.defender_hp.to_string(),
+        entry.damage_dealt.to_string(),
+        entry.damage_received.to_string(),
+        entry.is_kill().to_string(),
+    ]
+}
+
+/// Writes `rows` as a table in `format`. JSON writes the cells of the `strings` columns as
+/// strings and every other cell as is.
+fn write_table(
+    format: Format,
+    columns: &[&str],
+    strings: &[usize],
+    rows: impl ExactSizeIterator<Item = Vec<String>>,
+) -> String {
+    let mut out = String::new();
+
+    match format {
+        Format::Csv => {
+            out.push_str(&columns.join(","));
+            out.push('\n');
+            for row in rows {
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+        }
+        Format::Markdown => {
+            out.push_str(&format!("| {} |\n", columns.join(" | ")));
+            out.push_str(&format!("|{}\n", "---|".repeat(columns.len())));
+            for row in rows {
+                out.push_str(&format!("| {} |\n", row.join(" | ")));
+            }
+        }
+        Format::Json => {
+            out.push_str("[\n");
+            let n_rows = rows.len();
+            for (i, row) in rows.enumerate() {
+                let fields: Vec<String> = columns
+                    .iter()
+                    .zip(row)
+                    .enumerate()
+                    .map(|(column, (name, cell))| {
+                        if strings.contains(&column) {
+                            format!("\"{name}\": \"{cell}\"")
+                        } else {
+                            format!("\"{name}\": {cell}")
+                        }
+                    })
+                    .collect();
+                out.push_str(&format!("  {{{}}}", fields.join(", ")));
+                out.push_str(if i + 1 < n_rows { ",\n" } else { "\n" });
+            }
+            out.push_str("]\n");
+        }
+    }
+
+    out
+}
+
+/// Writes the entries as a table in `format`, one row per entry.
+pub fn export(entries: &[MatrixEntry], format: Format) -> String {
+    write_table(format, &COLUMNS, &[0, 1, 3, 4], entries.iter().map(cells))
+}
+
+/// One attack of a [`Sensitivity`] table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensitivityCell {
+    pub attacker_hp: Hp,
+    pub defender_hp: Hp,
+    pub damage_dealt: Hp,
+    /// Retaliation and tentacle damage the attacker takes.
+    pub damage_received: Hp,
+}
+
+impl SensitivityCell {
+    pub fn is_kill(&self) -> bool {
+        self.damage_dealt >= self.defender_hp
+    }
+}
+
+/// Damage both ways for one matchup over every attacker hp and every defender hp.
+#[derive(Debug, Clone)]
+pub struct Sensitivity {
+    pub attacker: Unit,
+    pub defender: Unit,
+    /// `cells[a][d]` is the attack of an attacker at `a + 1` hp on a defender at `d + 1` hp.
+    pub cells: Vec<Vec<SensitivityCell>>,
+}
+
+impl Sensitivity {
+    /// The attack of an attacker at `attacker_hp` on a defender at `defender_hp`, if both are
+    /// between 1 and their max hp.
+    pub fn cell(&self, attacker_hp: Hp, defender_hp: Hp) -> Option<&SensitivityCell> {
+        self.cells
+            .get(usize::from(attacker_hp).checked_sub(1)?)?
+            .get(usize::from(defender_hp).checked_sub(1)?)
+    }
+
+    /// For every attacker hp from 1 up, the highest defender hp it still kills, or 0 if it
+    /// kills nothing. These are the breakpoints between the kill and no-kill regions.
+    pub fn kill_thresholds(&self) -> Vec<Hp> {
+        self.cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .filter(|cell| cell.is_kill())
+                    .map(|cell| cell.defender_hp)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// Writes every cell as a table in `format`, one row per cell.
+    pub fn export(&self, format: Format) -> String {
+        let rows: Vec<Vec<String>> = self
+            .cells
+            .iter()
+            .flatten()
+            .map(|cell| {
+                vec![
+                    cell.attacker_hp.to_string(),
+                    cell.defender_hp.to_string(),
+                    cell.damage_dealt.to_string(),
+                    cell.damage_received.to_string(),
+                    cell.is_kill().to_string(),
+                ]
+            })
+            .collect();
+
+        write_table(
+            format,
+            &[
+                "attacker_hp",
+                "defender_hp",
+                "damage_dealt",
+                "damage_received",
+                "kill",
+            ],
+            &[],
+            rows.into_iter(),
+        )
+    }
+}
+
+impl fmt::Display for Sensitivity {
+    /// Writes a grid with a row per attacker hp and a column per defender hp. Each cell is
+    /// the damage dealt and received, with kills marked by `*`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} attacking {}, dealt/received",
+            self.attacker.unit_type, self.defender.unit_type
+        )?;
+
+        write!(f, "{:>4}", "")?;
+        for defender_hp in 1..=self.defender.max_hp {
+            write!(f, " {defender_hp:>6}")?;
+        }
+        writeln!(f)?;
+
+        for row in &self.cells {
+            write!(f, "{:>4}", row.first().map_or(0, |cell| cell.attacker_hp))?;
+            for cell in row {
+                let mark = if cell.is_kill() { "*" } else { "" };
+                let damage = format!("{mark}{}/{}", cell.damage_dealt, cell.damage_received);
+                write!(f, " {damage:>6}")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Sweeps the attacker and defender hp from 1 to their max hp, running every pair through
+/// [`single_combat`]. Everything else about the two units is kept as given.
+pub fn hp_sensitivity(attacker: &Unit, defender: &Unit) -> Sensitivity {
+    let cells = (1..=attacker.max_hp)
+        .map(|attacker_hp| {
+            let attacker = attacker.clone().with_current_hp(attacker_hp);
+
+            (1..=defender.max_hp)
+                .map(|defender_hp| {
+                    let defender = defender.clone().with_current_hp(defender_hp);
+                    let (to_attacker, to_defender) = single_combat(&attacker, &defender);
+
+                    SensitivityCell {
+                        attacker_hp,
+                        defender_hp,
+                        damage_dealt: to_defender.damage,
+                        damage_received: to_attacker.damage,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    Sensitivity {
+        attacker: attacker.clone(),
+        defender: defender.clone(),
+        cells,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combat::eliminate_defender;
+
+    use super::*;
+
+    #[test]
+    fn test_kn_vs_fortified_de() {
+        let entries = damage_matrix(&MatrixOptions {
+            attacker_tribe: Some(Tribe::Common),
+            defender_tribe: Some(Tribe::Common),
+            ..MatrixOptions::default()
+        });
+
+        let entry = entries
+            .iter()
+            .find(|entry| {
+                entry.attacker == UnitType::Knight
+                    && entry.defender == UnitType::Defender
+                    && entry.defender_effects == StatusEffects::FORTIFIED
+            })
+            .unwrap();
+        let (to_attacker, to_defender) = single_combat(
+            &Unit::new(UnitType::Knight),
+            &Unit::new(UnitType::Defender).with_status_effects(StatusEffects::FORTIFIED),
+        );
+
+        assert_eq!(entry.damage_dealt, to_defender.damage);
+        assert_eq!(entry.damage_received, to_attacker.damage);
+        assert!(
+            entries
+                .iter()
+                .all(|entry| entry.attacker.tribe() == Tribe::Common
+                    && entry.defender != UnitType::Mooni)
+        );
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let entries = damage_matrix(&MatrixOptions {
+            attacker_tribe: Some(Tribe::Polaris),
+            defender_tribe: Some(Tribe::Polaris),
+            attacker_hp: Some(5),
+            defender_effects: vec![StatusEffects::empty()],
+            ..MatrixOptions::default()
+        });
+        assert!(entries.iter().all(|entry| entry.attacker_hp <= 5));
+
+        let csv = export(&entries, Format::Csv);
+        assert_eq!(csv.lines().count(), entries.len() + 1);
+        assert!(csv.starts_with("attacker,attacker_effects,"));
+
+        let markdown = export(&entries, Format::Markdown);
+        assert_eq!(markdown.lines().count(), entries.len() + 2);
+
+        let json = export(&entries[..1], Format::Json);
+        assert!(json.contains("\"attacker\": \"Archer\""));
+        assert_eq!("md".parse(), Ok(Format::Markdown));
+    }
+
+    #[test]
+    fn test_sensitivity_matches_eliminate() {
+        let attacker = Unit::new(UnitType::Swordsman);
+        let defender = Unit::new(UnitType::Warrior);
+
+        let table = hp_sensitivity(&attacker, &defender);
+
+        assert_eq!(table.cells.len(), 15);
+        assert_eq!(table.cells[0].len(), 10);
+        let thresholds = table.kill_thresholds();
+        assert_eq!(
+            thresholds[14],
+            eliminate_defender(&attacker, defender.clone()).unwrap()
+        );
+        assert!(thresholds.is_sorted());
+
+        let cell = table.cell(15, 10).unwrap();
+        let (to_attacker, to_defender) = single_combat(&attacker, &defender);
+        assert_eq!(cell.damage_dealt, to_defender.damage);
+        assert_eq!(cell.damage_received, to_attacker.damage);
+        assert!(table.cell(0, 10).is_none());
+
+        assert_eq!(table.to_string().lines().count(), 15 + 2);
+        assert_eq!(table.export(Format::Csv).lines().count(), 15 * 10 + 1);
+    }
+}
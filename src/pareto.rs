@@ -0,0 +1,184 @@
+use std::cmp::Reverse;
+
+use crate::{
+    combat::{CombatLog, multi_combat_log, multi_combat_outcome},
+    unit::{Hp, Units},
+    utils::Perms,
+};
+
+/// What a plan achieves, from the attackers' side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PlanMetrics {
+    /// Defenders killed or converted.
+    pub kills: usize,
+    /// Hp taken off the defenders, kills included.
+    pub damage_dealt: u32,
+    /// Attackers killed.
+    pub attackers_lost: usize,
+    /// Hp the attackers lost, deaths included.
+    pub hp_lost: u32,
+}
+
+impl PlanMetrics {
+    /// Measures the volley of `attackers` on `defenders` in the given orders.
+    pub fn of(attackers: &Units, defenders: Units) -> Self {
+        let outcome = multi_combat_outcome(attackers, defenders.clone());
+        let lost_hp = |before: Hp, after: Hp| u32::from(before.saturating_sub(after));
+
+        let mut metrics = PlanMetrics::default();
+        for (before, after) in defenders.iter().zip(&outcome.defenders) {
+            metrics.damage_dealt += lost_hp(before.current_hp, after.current_hp);
+            metrics.kills += usize::from(before.current_hp > 0 && after.current_hp == 0);
+        }
+        // Segments grown during the volley come after the attackers and are left out.
+        for (before, after) in attackers.iter().zip(&outcome.attackers) {
+            metrics.hp_lost += lost_hp(before.current_hp, after.current_hp);
+            metrics.attackers_lost += usize::from(before.current_hp > 0 && after.current_hp == 0);
+        }
+
+        metrics
+    }
+
+    /// Whether this plan is at least as good as `other` on every count and better on one.
+    pub fn dominates(&self, other: &PlanMetrics) -> bool {
+        let at_least = self.kills >= other.kills
+            && self.damage_dealt >= other.damage_dealt
+            && self.attackers_lost <= other.attackers_lost
+            && self.hp_lost <= other.hp_lost;
+
+        at_least && self != other
+    }
+}
+
+/// One plan on the Pareto front.
+#[derive(Debug)]
+pub struct Plan {
+    pub metrics: PlanMetrics,
+    /// The attackers, in the order they attack.
+    pub attackers: Units,
+    /// The defenders, in the order they are attacked.
+    pub defenders: Units,
+    pub log: CombatLog,
+}
+
+/// Finds every attack order that no other order beats on kills, damage dealt, attackers
+/// lost and hp lost all at once, so the choice between a safe and an aggressive plan is
+/// left to the player.
+///
+/// Orders with the same metrics are only listed once. Plans are sorted by kills, most first,
+/// then by hp lost, least first.
+pub fn pareto_front(mut attackers: Units, mut defenders: Units) -> Vec<Plan> {
+    let mut front: Vec<(PlanMetrics, Units, Units)> = Vec::new();
+    let mut consider = |attackers: &Units, defenders: &Units| {
+        let metrics = PlanMetrics::of(attackers, defenders.clone());
+        if front
+            .iter()
+            .any(|(other, _, _)| *other == metrics || other.dominates(&metrics))
+        {
+            return;
+        }
+
+        front.retain(|(other, _, _)| !metrics.dominates(other));
+        front.push((metrics, attackers.clone(), defenders.clone()));
+    };
+
+    if attackers.is_empty() || defenders.is_empty() {
+        consider(&attackers, &defenders);
+    } else {
+        for (a_first, a_second) in Perms::new(attackers.len()) {
+            attackers.swap(a_first, a_second);
+
+            for (d_first, d_second) in Perms::new(defenders.len()) {
+                defenders.swap(d_first, d_second);
+                consider(&attackers, &defenders);
+            }
+        }
+    }
+
+    front.sort_by_key(|(metrics, _, _)| (Reverse(metrics.kills), metrics.hp_lost));
+    front
+        .into_iter()
+        .map(|(metrics, attackers, defenders)| Plan {
+            metrics,
+            log: multi_combat_log(&attackers, defenders.clone()),
+            attackers,
+            defenders,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::{Unit, UnitType};
+
+    use super::*;
+
+    #[test]
+    fn test_front_has_safe_and_aggressive_plans() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Catapult).with_current_hp(2),
+            Unit::new(UnitType::Warrior),
+            Unit::new(UnitType::Warrior).with_current_hp(3),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Warrior).with_current_hp(8),
+            Unit::new(UnitType::Archer).with_current_hp(8),
+        ]);
+
+        let front = pareto_front(attackers, defenders);
+
+        let metrics: Vec<_> = front.iter().map(|plan| plan.metrics).collect();
+        assert_eq!(
+            metrics,
+            [
+                // Both kills, sacrificing the hurt warrior.
+                PlanMetrics {
+                    kills: 2,
+                    damage_dealt: 16,
+                    attackers_lost: 1,
+                    hp_lost: 3,
+                },
+                // Both kills without a loss, but more damage taken.
+                PlanMetrics {
+                    kills: 2,
+                    damage_dealt: 16,
+                    attackers_lost: 0,
+                    hp_lost: 4,
+                },
+                // One kill for a scratch.
+                PlanMetrics {
+                    kills: 1,
+                    damage_dealt: 14,
+                    attackers_lost: 0,
+                    hp_lost: 1,
+                },
+            ]
+        );
+        for plan in &front {
+            assert_eq!(
+                PlanMetrics::of(&plan.attackers, plan.defenders.clone()),
+                plan.metrics
+            );
+        }
+    }
+
+    #[test]
+    fn test_dominates() {
+        let safe = PlanMetrics {
+            kills: 1,
+            damage_dealt: 10,
+            attackers_lost: 0,
+            hp_lost: 2,
+        };
+        let aggressive = PlanMetrics {
+            kills: 2,
+            damage_dealt: 20,
+            attackers_lost: 1,
+            hp_lost: 12,
+        };
+
+        assert!(!safe.dominates(&aggressive) && !aggressive.dominates(&safe));
+        assert!(safe.dominates(&PlanMetrics { hp_lost: 5, ..safe }));
+        assert!(!safe.dominates(&safe));
+    }
+}
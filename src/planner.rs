@@ -0,0 +1,327 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    battlefield::Terrain,
+    board::{Board, Position},
+    combat::{CombatEvent, CombatLog, EventKind, single_combat, tentacle_strikes},
+    unit::{StatusEffects, Unit, Units},
+    utils::Perms,
+};
+
+/// Whether a unit of the given kind can stand on `terrain`.
+fn can_enter(terrain: Terrain, naval: bool) -> bool {
+    matches!(terrain, Terrain::Water | Terrain::Ocean) == naval
+}
+
+/// Finds every tile `unit` can end its move on, including the tile it stands on.
+///
+/// Each step costs one movement. Entering a forest or mountain, or a tile next to an enemy
+/// (zone of control), ends the move. Units cannot move through other units, and naval units
+/// stay on water while land units stay on land.
+pub fn reachable(board: &Board, unit: &Unit, friends: &Units, enemies: &Units) -> Vec<Position> {
+    let Some(start) = unit.position else {
+        return Vec::new();
+    };
+
+    let naval = unit.unit_type.is_naval();
+    let occupied = |position: Position| {
+        friends
+            .alive()
+            .chain(enemies.alive())
+            .any(|other| other.position == Some(position))
+    };
+    let in_enemy_zone = |position: Position| {
+        enemies
+            .alive()
+            .any(|enemy| enemy.position.is_some_and(|p| p.is_adjacent(position)))
+    };
+
+    let mut best: HashMap<Position, u8> = HashMap::from([(start, unit.movement)]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(position) = queue.pop_front() {
+        let remaining = best[&position];
+        if remaining == 0 {
+            continue;
+        }
+
+        for next in position.neighbours() {
+            let Some(terrain) = board.terrain(next) else {
+                continue;
+            };
+            if !can_enter(terrain, naval) || occupied(next) {
+                continue;
+            }
+
+            let left =
+                if matches!(terrain, Terrain::Forest | Terrain::Mountain) || in_enemy_zone(next) {
+                    0
+                } else {
+                    remaining - 1
+                };
+
+            if best.get(&next).is_none_or(|&known| known < left) {
+                best.insert(next, left);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut tiles: Vec<Position> = best.into_keys().collect();
+    tiles.sort_by_key(|tile| (tile.distance(start), *tile));
+    tiles
+}
+
+/// The best move and attack for one attacker: where to stand, which defender to hit, and
+/// how the trade scores.
+struct Action {
+    tile: Position,
+    defender: usize,
+    score: i32,
+}
+
+fn best_action(
+    board: &Board,
+    attacker: usize,
+    attackers: &Units,
+    defenders: &Units,
+) -> Option<Action> {
+    let unit = &attackers[attacker];
+    let mut best: Option<Action> = None;
+
+    for tile in reachable(board, unit, attackers, defenders) {
+        let moved = unit.clone().with_position(tile);
+
+        for (d_idx, defender) in defenders.iter().enumerate() {
+            let Some(target) = defender.position else {
+                continue;
+            };
+            if defender.current_hp == 0
+                || target.distance(tile) > u16::from(moved.range)
+                || !(target.distance(tile) <= board.vision(tile)
+                    || board.is_visible(target, attackers))
+            {
+                continue;
+            }
+
+            let (to_attacker, to_defender) = single_combat(&moved, defender);
+
+            let mut score = i32::from(to_defender.damage.min(defender.current_hp))
+                - i32::from(to_attacker.damage.min(moved.current_hp));
+            if to_defender.damage >= defender.current_hp {
+                score += 100;
+            }
+
+            if best.as_ref().is_none_or(|action| score > action.score) {
+                best = Some(Action {
+                    tile,
+                    defender: d_idx,
+                    score,
+                });
+            }
+        }
+    }
+
+    best
+}
+
+/// Lets each attacker in order move and attack greedily, returning the total score and the
+/// steps taken. Attackers with nothing to attack stay put.
+fn plan_order(board: &Board, mut attackers: Units, mut defenders: Units) -> (i32, CombatLog) {
+    let mut log = CombatLog::new();
+    let mut score = 0;
+
+    for a_idx in 0..attackers.len() {
+        let Some(action) = best_action(board, a_idx, &attackers, &defenders) else {
+            continue;
+        };
+
+        let start = attackers[a_idx].position;
+        attackers[a_idx].position = Some(action.tile);
+
+        let attacker = &mut attackers[a_idx];
+
+        // Moving next to tentacled defenders other than the target gets the attacker struck.
+        if start != Some(action.tile) {
+            for (t_idx, damage) in tentacle_strikes(attacker, &defenders, action.defender) {
+                log.push(CombatEvent::strike(
+                    EventKind::Tentacles,
+                    &defenders[t_idx],
+                    attacker,
+                    damage,
+                    StatusEffects::empty(),
+                ));
+                score -= i32::from(damage.min(attacker.current_hp));
+                attacker.current_hp = attacker.current_hp.saturating_sub(damage);
+            }
+            if attacker.current_hp == 0 {
+                continue;
+            }
+        }
+
+        let defender = &mut defenders[action.defender];
+        let (to_attacker, to_defender) = single_combat(attacker, defender);
+        let killed = to_defender.damage >= defender.current_hp;
+
+        log.push(CombatEvent {
+            kind: EventKind::Attack,
+            attacker: attacker.clone(),
+            defender: defender.clone(),
+            damage_to_attacker: to_attacker.damage,
+            damage_to_defender: to_defender.damage,
+            status_effects_to_attacker: to_attacker.status_effects,
+            status_effects_to_defender: to_defender.status_effects,
+            moved_from: start.filter(|&start| start != action.tile),
+            kills: attacker.kills.saturating_add(u8::from(killed)),
+        });
+        score += i32::from(to_defender.damage.min(defender.current_hp))
+            - i32::from(to_attacker.damage.min(attacker.current_hp));
+        if killed {
+            score += 100;
+        }
+
+        attacker.current_hp = attacker.current_hp.saturating_sub(to_attacker.damage);
+        attacker.apply_status_effects(to_attacker.status_effects);
+
+        if killed {
+            attacker.kills = attacker.kills.saturating_add(1);
+            defender.current_hp = 0;
+            // Melee attackers take the tile of the unit they kill.
+            if attacker.range == 1 && attacker.current_hp > 0 {
+                attacker.position = defender.position;
+            }
+        } else {
+            defender.current_hp -= to_defender.damage;
+            defender.apply_status_effects(to_defender.status_effects);
+        }
+    }
+
+    (score, log)
+}
+
+/// Finds the attack order where moving each attacker to its best tile and target scores
+/// highest.
+///
+/// Every attacker and defender needs a position on `board`. Defenders get their terrain
+/// bonus like in [`Board::optimized`]. Each step of the returned log records where the
+/// attacker moved from, if it moved.
+pub fn plan(board: &Board, mut attackers: Units, defenders: Units) -> (i32, CombatLog) {
+    let defenders = board.place_defenders(defenders);
+    let mut best = (i32::MIN, CombatLog::new());
+
+    for (a_first, a_second) in Perms::new(attackers.len()) {
+        attackers.swap(a_first, a_second);
+
+        let (score, log) = plan_order(board, attackers.clone(), defenders.clone());
+        if score > best.0 {
+            best = (score, log);
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{battlefield::Techs, unit::UnitType};
+
+    use super::*;
+
+    #[test]
+    fn test_reachable_zone_of_control() {
+        let board = Board::new(8, 1);
+        let knight = Unit::new(UnitType::Knight).with_position(Position::new(0, 0));
+        let enemies =
+            Units::from([Unit::new(UnitType::Warrior).with_position(Position::new(3, 0))]);
+
+        let tiles = reachable(&board, &knight, &Units::from([knight.clone()]), &enemies);
+
+        assert_eq!(
+            tiles,
+            [
+                Position::new(0, 0),
+                Position::new(1, 0),
+                Position::new(2, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reachable_forest_and_water() {
+        let board = Board::new(8, 1)
+            .with_terrain(Position::new(1, 0), Terrain::Forest)
+            .with_terrain(Position::new(3, 0), Terrain::Water);
+        let rider = Unit::new(UnitType::Rider).with_position(Position::new(0, 0));
+        let boat = Unit::new(UnitType::WarriorBomber).with_position(Position::new(3, 0));
+
+        let tiles = reachable(&board, &rider, &Units::new(), &Units::new());
+        assert_eq!(tiles, [Position::new(0, 0), Position::new(1, 0)]);
+
+        let tiles = reachable(&board, &boat, &Units::new(), &Units::new());
+        assert_eq!(tiles, [Position::new(3, 0)]);
+    }
+
+    #[test]
+    fn test_plan_tentacles_on_move_in() {
+        let board = Board::new(8, 8)
+            .with_terrain(Position::new(2, 0), Terrain::Water)
+            .with_terrain(Position::new(2, 2), Terrain::Water);
+        let attackers =
+            Units::from([Unit::new(UnitType::Rider).with_position(Position::new(0, 1))]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Jelly).with_position(Position::new(2, 0)),
+            Unit::new(UnitType::Warrior)
+                .with_current_hp(10)
+                .with_position(Position::new(2, 1)),
+            Unit::new(UnitType::Jelly).with_position(Position::new(2, 2)),
+        ]);
+
+        let (score, log) = plan(&board, attackers, defenders);
+
+        assert_eq!(log.score(), score);
+        assert!(log.iter().any(|event| event.kind == EventKind::Tentacles));
+    }
+
+    #[test]
+    fn test_plan_terrain_bonus() {
+        let board = Board::new(4, 1).with_terrain(Position::new(1, 0), Terrain::Mountain);
+        let attackers =
+            Units::from([Unit::new(UnitType::Warrior).with_position(Position::new(0, 0))]);
+        let defenders = Units::from([Unit::new(UnitType::Warrior)
+            .with_current_hp(6)
+            .with_position(Position::new(1, 0))]);
+
+        let (_, log) = plan(&board, attackers.clone(), defenders.clone());
+        assert!(log[0].is_kill());
+
+        // Climbing gives the defender its mountain bonus, and it survives.
+        let board = board.with_defender_techs(Techs::CLIMBING);
+        let (score, log) = plan(&board, attackers, defenders);
+        assert!(!log[0].is_kill());
+        assert_eq!(log.score(), score);
+    }
+
+    #[test]
+    fn test_plan_moves_into_range() {
+        let board = Board::new(8, 8);
+        let attackers = Units::from([
+            Unit::new(UnitType::Rider).with_position(Position::new(0, 0)),
+            Unit::new(UnitType::Warrior).with_position(Position::new(2, 1)),
+        ]);
+        let defenders = Units::from([Unit::new(UnitType::Warrior)
+            .with_current_hp(5)
+            .with_position(Position::new(3, 0))]);
+
+        let (score, log) = plan(&board, attackers, defenders);
+
+        assert!(score > 100);
+        assert_eq!(log.len(), 1);
+        assert!(
+            log[0]
+                .attacker
+                .position
+                .unwrap()
+                .is_adjacent(Position::new(3, 0))
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use std::{fmt, ops::RangeInclusive};
+
+use crate::{
+    combat::{CombatLog, multi_combat_log, multi_combat_outcome, multi_combat_score},
+    unit::{Hp, StatusEffects, Unit, Units},
+    utils::Perms,
+};
+
+/// What is not known about one defender, referred to by its index in the defenders given to
+/// [`robust_plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uncertainty {
+    pub defender: usize,
+    /// The hp it might have. Its current hp if `None`.
+    pub hp: Option<RangeInclusive<Hp>>,
+    /// Status effects it might have on top of its own, such as fortified or walled. Its own
+    /// only if empty.
+    pub status_effects: Vec<StatusEffects>,
+}
+
+impl Uncertainty {
+    /// Every version of `unit` this allows for, or an error if the hp range is empty or
+    /// reaches outside `1..=max_hp` for any of the status effects.
+    fn guesses(&self, unit: &Unit) -> Result<Vec<Unit>, UncertaintyError> {
+        let status_effects = if self.status_effects.is_empty() {
+            &[StatusEffects::empty()][..]
+        } else {
+            &self.status_effects
+        };
+        let versions: Vec<Unit> = status_effects
+            .iter()
+            .map(|&effects| unit.clone().with_status_effects(effects))
+            .collect();
+
+        let hps = match &self.hp {
+            None => return Ok(versions),
+            Some(hps) if hps.is_empty() => return Err(UncertaintyError::EmptyRange),
+            Some(hps) => hps.clone(),
+        };
+        if *hps.start() == 0 || versions.iter().any(|version| *hps.end() > version.max_hp) {
+            return Err(UncertaintyError::InvalidHp);
+        }
+
+        Ok(hps
+            .flat_map(|hp| {
+                versions
+                    .iter()
+                    .map(move |version| version.clone().with_current_hp(hp))
+            })
+            .collect())
+    }
+}
+
+/// How a plan is scored across every way the uncertainties can turn out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Objective {
+    /// Its lowest score.
+    #[default]
+    WorstCase,
+    /// Its mean score, with every case equally likely.
+    Expected,
+}
+
+/// The most combinations of guesses [`robust_plan`] scores every order against.
+pub const MAX_SCENARIOS: usize = 1024;
+
+/// Why [`robust_plan`] cannot plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UncertaintyError {
+    /// An uncertainty refers to a defender that does not exist.
+    OutOfRange,
+    /// An hp range holds no hp, so the defender cannot be anything.
+    EmptyRange,
+    /// An hp range reaches below 1 or above the defender's max hp.
+    InvalidHp,
+    /// The guesses combine into more than [`MAX_SCENARIOS`] scenarios.
+    TooManyScenarios,
+}
+
+impl fmt::Display for UncertaintyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => {
+                write!(f, "an uncertainty refers to a defender that does not exist")
+            }
+            Self::EmptyRange => write!(f, "an hp range is empty"),
+            Self::InvalidHp => write!(f, "an hp range reaches outside 1 to the max hp"),
+            Self::TooManyScenarios => {
+                write!(
+                    f,
+                    "the guesses combine into more than {MAX_SCENARIOS} scenarios"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for UncertaintyError {}
+
+/// A single guess that, on its own, changes how many defenders a plan kills.
+#[derive(Debug, Clone)]
+pub struct Flip {
+    /// The index of the defender, as in [`Uncertainty::defender`].
+    pub defender: usize,
+    /// The defender as guessed.
+    pub guess: Unit,
+    /// The kills with the guess instead of the defender as given.
+    pub kills: usize,
+}
+
+/// The plan that does best across the uncertainties.
+#[derive(Debug)]
+pub struct RobustPlan {
+    /// The plan's score under the [`Objective`].
+    pub score: i32,
+    /// The attackers, in the order they attack.
+    pub attackers: Units,
+    /// The defenders as given, in the order they are attacked.
+    pub defenders: Units,
+    /// The plan's log against the defenders as given.
+    pub log: CombatLog,
+    /// The kills against the defenders as given.
+    pub kills: usize,
+    /// The guesses that change the kills.
+    pub flips: Vec<Flip>,
+}
+
+fn kills(attackers: &Units, defenders: Units) -> usize {
+    multi_combat_outcome(attackers, defenders)
+        .defenders
+        .iter()
+        .filter(|unit| unit.current_hp == 0)
+        .count()
+}
+
+/// Every combination of guesses, as whole sides in the order the defenders were given.
+fn scenarios(defenders: &Units, guesses: &[(usize, Vec<Unit>)]) -> Vec<Units> {
+    let mut scenarios = vec![defenders.clone()];
+    for (defender, guesses) in guesses {
+        scenarios = scenarios
+            .iter()
+            .flat_map(|scenario| {
+                guesses.iter().map(move |guess| {
+                    let mut scenario = scenario.clone();
+                    scenario[*defender] = guess.clone();
+                    scenario
+                })
+            })
+            .collect();
+    }
+    scenarios
+}
+
+/// Finds the attack order that scores best, by [`multi_combat_score`], across every way the
+/// `uncertainties` can turn out, and the single guesses that would change its kills.
+///
+/// Every combination of guesses is tried for every order, so the work multiplies with the
+/// size of each hp range and the number of status effect alternatives. Fails if there are
+/// more than [`MAX_SCENARIOS`] combinations.
+pub fn robust_plan(
+    attackers: Units,
+    defenders: Units,
+    uncertainties: &[Uncertainty],
+    objective: Objective,
+) -> Result<RobustPlan, UncertaintyError> {
+    let mut guesses = Vec::new();
+    for uncertainty in uncertainties {
+        let unit = defenders
+            .iter()
+            .nth(uncertainty.defender)
+            .ok_or(UncertaintyError::OutOfRange)?;
+        guesses.push((uncertainty.defender, uncertainty.guesses(unit)?));
+    }
+    let n_scenarios = guesses
+        .iter()
+        .try_fold(1usize, |n, (_, guesses)| n.checked_mul(guesses.len()));
+    if n_scenarios.is_none_or(|n| n > MAX_SCENARIOS) {
+        return Err(UncertaintyError::TooManyScenarios);
+    }
+    let scenarios = scenarios(&defenders, &guesses);
+
+    let score_order = |attackers: &Units, defender_order: &[usize]| {
+        let scores = scenarios.iter().map(|scenario| {
+            let defenders = defender_order
+                .iter()
+                .map(|&d| scenario[d].clone())
+                .collect();
+            multi_combat_score(attackers, defenders)
+        });
+        match objective {
+            Objective::WorstCase => scores.min().unwrap_or(0),
+            Objective::Expected => {
+                (scores.map(i64::from).sum::<i64>() / scenarios.len() as i64) as i32
+            }
+        }
+    };
+
+    let mut attackers = attackers;
+    let mut defender_order: Vec<usize> = (0..defenders.len()).collect();
+    let mut best = (i32::MIN, attackers.clone(), defender_order.clone());
+    if attackers.is_empty() || defenders.is_empty() {
+        best.0 = score_order(&attackers, &defender_order);
+    } else {
+        for (a_first, a_second) in Perms::new(attackers.len()) {
+            attackers.swap(a_first, a_second);
+
+            for (d_first, d_second) in Perms::new(defenders.len()) {
+                defender_order.swap(d_first, d_second);
+
+                let score = score_order(&attackers, &defender_order);
+                if score > best.0 {
+                    best = (score, attackers.clone(), defender_order.clone());
+                }
+            }
+        }
+    }
+
+    let (score, attackers, defender_order) = best;
+    let in_order = |scenario: &Units| -> Units {
+        defender_order
+            .iter()
+            .map(|&d| scenario[d].clone())
+            .collect()
+    };
+    let ordered = in_order(&defenders);
+    let baseline = kills(&attackers, ordered.clone());
+
+    let mut flips = Vec::new();
+    for (defender, guesses) in &guesses {
+        for guess in guesses {
+            let mut scenario = defenders.clone();
+            scenario[*defender] = guess.clone();
+            let kills = kills(&attackers, in_order(&scenario));
+            if kills != baseline {
+                flips.push(Flip {
+                    defender: *defender,
+                    guess: guess.clone(),
+                    kills,
+                });
+            }
+        }
+    }
+
+    Ok(RobustPlan {
+        score,
+        log: multi_combat_log(&attackers, ordered.clone()),
+        attackers,
+        defenders: ordered,
+        kills: baseline,
+        flips,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::UnitType;
+
+    use super::*;
+
+    #[test]
+    fn test_fortified_defender_flips_kill() {
+        let attackers = Units::from([Unit::new(UnitType::Swordsman)]);
+        let defenders = Units::from([Unit::new(UnitType::Warrior).with_current_hp(5)]);
+        let uncertainties = [Uncertainty {
+            defender: 0,
+            hp: Some(4..=6),
+            status_effects: vec![StatusEffects::empty(), StatusEffects::WALLED],
+        }];
+
+        let plan = robust_plan(
+            attackers.clone(),
+            defenders.clone(),
+            &uncertainties,
+            Objective::WorstCase,
+        )
+        .unwrap();
+
+        assert_eq!(plan.kills, 1);
+        assert!(!plan.flips.is_empty());
+        assert!(plan.flips.iter().all(|flip| flip.kills == 0));
+        // The worst case is a full hp walled warrior, which survives.
+        let worst = defenders[0]
+            .clone()
+            .with_current_hp(6)
+            .with_status_effects(StatusEffects::WALLED);
+        assert_eq!(
+            plan.score,
+            multi_combat_score(&attackers, Units::from([worst]))
+        );
+
+        let expected =
+            robust_plan(attackers, defenders, &uncertainties, Objective::Expected).unwrap();
+        assert!(expected.score > plan.score);
+    }
+
+    #[test]
+    fn test_uncertainty_errors() {
+        let attackers = Units::from([Unit::new(UnitType::Warrior)]);
+        let defenders = Units::from([Unit::new(UnitType::Warrior)]);
+        let out_of_range = [Uncertainty {
+            defender: 1,
+            hp: None,
+            status_effects: vec![StatusEffects::FORTIFIED],
+        }];
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = [Uncertainty {
+            defender: 0,
+            hp: Some(5..=4),
+            status_effects: Vec::new(),
+        }];
+
+        assert_eq!(
+            robust_plan(
+                attackers.clone(),
+                defenders.clone(),
+                &out_of_range,
+                Objective::WorstCase
+            )
+            .unwrap_err(),
+            UncertaintyError::OutOfRange
+        );
+        assert_eq!(
+            robust_plan(
+                attackers.clone(),
+                defenders.clone(),
+                &empty,
+                Objective::WorstCase
+            )
+            .unwrap_err(),
+            UncertaintyError::EmptyRange
+        );
+
+        for hp in [0..=5, 5..=11] {
+            let invalid = [Uncertainty {
+                defender: 0,
+                hp: Some(hp),
+                status_effects: Vec::new(),
+            }];
+            assert_eq!(
+                robust_plan(
+                    attackers.clone(),
+                    defenders.clone(),
+                    &invalid,
+                    Objective::WorstCase
+                )
+                .unwrap_err(),
+                UncertaintyError::InvalidHp
+            );
+        }
+        // Veterans have more hp to guess at.
+        let veteran = [Uncertainty {
+            defender: 0,
+            hp: Some(5..=15),
+            status_effects: vec![StatusEffects::VETERAN],
+        }];
+        assert!(robust_plan(attackers.clone(), defenders, &veteran, Objective::WorstCase).is_ok());
+
+        let giants = Units::from([Unit::new(UnitType::Giant), Unit::new(UnitType::Giant)]);
+        let vague = [0, 1].map(|defender| Uncertainty {
+            defender,
+            hp: Some(1..=40),
+            status_effects: vec![StatusEffects::empty(), StatusEffects::FORTIFIED],
+        });
+        assert_eq!(
+            robust_plan(attackers, giants, &vague, Objective::WorstCase).unwrap_err(),
+            UncertaintyError::TooManyScenarios
+        );
+    }
+}
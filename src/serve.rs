@@ -0,0 +1,579 @@
+//! A small HTTP server that answers JSON requests with the calculator's results.
+//!
+//! Every endpoint but `GET /openapi.json` takes a `POST` whose body is either a battle in
+//! chat shorthand, e.g. `wa, ca v / gi 30`, or a JSON scenario. See `openapi.json` for the
+//! shapes of requests and responses.
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Take, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use polycalculato_rs::combat::{
+    CombatEvent, CombatLog, bulk_copies, eliminate_attacker, eliminate_defender, multi_combat_log,
+    optimized, single_combat,
+};
+use polycalculato_rs::pareto::{Plan, pareto_front};
+use polycalculato_rs::unit::{MAX_UNITS, StatusEffects, Unit, UnitType, Units, parse_battle};
+use serde_json::{Value, json};
+
+const OPENAPI: &str = include_str!("openapi.json");
+
+/// Room for one unit in a request, enough for a JSON unit with every field spelled out.
+const BYTES_PER_UNIT: usize = 256;
+/// The largest body accepted: two full sides plus room for the rest of the scenario.
+pub const MAX_BODY_BYTES: usize = 2 * MAX_UNITS * BYTES_PER_UNIT + 1024;
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+/// The most attacker and defender orders `/optimize` tries, as attacker orders times
+/// defender orders, so that one request cannot keep a thread busy for minutes.
+pub const MAX_ORDERS: u64 = 1_000_000;
+/// How long a client may take to send its whole request, and then to read the response.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+/// The most connections handled at once. Further connections are turned away with a 503.
+const MAX_CONNECTIONS: usize = 16;
+
+/// A status code and a JSON body.
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self {
+            status: 200,
+            body: body.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.to_string() }).to_string(),
+        }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn unit_json(unit: &Unit) -> Value {
+    json!({
+        "type": unit.unit_type.to_string(),
+        "hp": unit.current_hp,
+        "max_hp": unit.max_hp,
+        "status_effects": format!("{:#}", unit.status_effects),
+    })
+}
+
+fn event_json(event: &CombatEvent) -> Value {
+    json!({
+        "kind": format!("{:?}", event.kind),
+        "attacker": unit_json(&event.attacker),
+        "defender": unit_json(&event.defender),
+        "damage_to_attacker": event.damage_to_attacker,
+        "damage_to_defender": event.damage_to_defender,
+        "status_effects_to_attacker": format!("{:#}", event.status_effects_to_attacker),
+        "status_effects_to_defender": format!("{:#}", event.status_effects_to_defender),
+        "kill": event.is_kill(),
+        "kills": event.kills,
+    })
+}
+
+fn log_json(score: i32, log: &CombatLog) -> Value {
+    json!({
+        "score": score,
+        "events": log.iter().map(event_json).collect::<Vec<_>>(),
+    })
+}
+
+fn plan_json(plan: &Plan) -> Value {
+    json!({
+        "kills": plan.metrics.kills,
+        "damage_dealt": plan.metrics.damage_dealt,
+        "attackers_lost": plan.metrics.attackers_lost,
+        "hp_lost": plan.metrics.hp_lost,
+        "events": plan.log.iter().map(event_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Parses a unit given as shorthand or as `{"type", "hp", "status_effects"}`.
+fn parse_unit(value: &Value) -> Result<Unit, Response> {
+    let bad = |message: String| Response::error(400, message);
+
+    if let Some(shorthand) = value.as_str() {
+        return shorthand.parse().map_err(|err| bad(format!("{err}")));
+    }
+    let object = value
+        .as_object()
+        .ok_or_else(|| bad("a unit must be a string or an object".to_owned()))?;
+
+    let unit_type: UnitType = object
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| bad("a unit needs a `type`".to_owned()))?
+        .parse()
+        .map_err(|err| bad(format!("{err}")))?;
+    let status_effects: StatusEffects = match object.get("status_effects") {
+        None | Some(Value::Null) => StatusEffects::empty(),
+        Some(effects) => effects
+            .as_str()
+            .ok_or_else(|| bad("`status_effects` must be a string".to_owned()))?
+            .parse()
+            .map_err(|err| bad(format!("{err}")))?,
+    };
+
+    let unit = Unit::new(unit_type).with_status_effects(status_effects);
+    match object.get("hp") {
+        None | Some(Value::Null) => Ok(unit),
+        Some(hp) => match hp.as_u64() {
+            Some(hp) if hp > 0 && hp <= u64::from(unit.max_hp) => Ok(unit.with_current_hp(hp as _)),
+            _ => Err(bad(format!("invalid hp {hp} for {unit_type}"))),
+        },
+    }
+}
+
+fn parse_side(value: Option<&Value>, name: &str) -> Result<Units, Response> {
+    let units = value
+        .and_then(Value::as_array)
+        .ok_or_else(|| Response::error(400, format!("`{name}` must be an array of units")))?;
+    if units.len() > MAX_UNITS {
+        return Err(Response::error(
+            413,
+            format!("{} {name}, at most {MAX_UNITS} are allowed", units.len()),
+        ));
+    }
+
+    units.iter().map(parse_unit).collect()
+}
+
+/// Parses a body in battle shorthand, or a JSON scenario with either a `battle` in shorthand
+/// or `attackers` and `defenders` arrays.
+fn parse_scenario(body: &str) -> Result<(Units, Units), Response> {
+    let body = body.trim();
+    if !body.starts_with('{') {
+        return parse_battle(body).map_err(|err| Response::error(400, err));
+    }
+
+    let scenario: Value = serde_json::from_str(body).map_err(|err| Response::error(400, err))?;
+    if let Some(battle) = scenario.get("battle").and_then(Value::as_str) {
+        return parse_battle(battle).map_err(|err| Response::error(400, err));
+    }
+
+    Ok((
+        parse_side(scenario.get("attackers"), "attackers")?,
+        parse_side(scenario.get("defenders"), "defenders")?,
+    ))
+}
+
+/// Whether a JSON scenario sets the option `name` to `true`. Shorthand bodies set none.
+fn option(body: &str, name: &str) -> bool {
+    serde_json::from_str::<Value>(body.trim())
+        .is_ok_and(|scenario| scenario.get(name).and_then(Value::as_bool) == Some(true))
+}
+
+/// Parses a scenario with exactly one unit on each side.
+fn parse_duel(body: &str) -> Result<(Unit, Unit), Response> {
+    let (attackers, defenders) = parse_scenario(body)?;
+    match (attackers.iter().next(), defenders.iter().next()) {
+        (Some(attacker), Some(defender)) if attackers.len() == 1 && defenders.len() == 1 => {
+            Ok((attacker.clone(), defender.clone()))
+        }
+        _ => Err(Response::error(
+            400,
+            "expected exactly one attacker and one defender",
+        )),
+    }
+}
+
+fn single(body: &str) -> Result<Value, Response> {
+    let (attacker, defender) = parse_duel(body)?;
+    let (to_attacker, to_defender) = single_combat(&attacker, &defender);
+
+    Ok(json!({
+        "attacker": unit_json(&attacker),
+        "defender": unit_json(&defender),
+        "damage_to_attacker": to_attacker.damage,
+        "damage_to_defender": to_defender.damage,
+        "attacker_hp": attacker.current_hp.saturating_sub(to_attacker.damage),
+        "defender_hp": defender.current_hp.saturating_sub(to_defender.damage),
+    }))
+}
+
+fn multi(body: &str) -> Result<Value, Response> {
+    let (attackers, defenders) = parse_scenario(body)?;
+    let log = multi_combat_log(&attackers, defenders);
+
+    Ok(log_json(log.score(), &log))
+}
+
+/// The orders `/optimize` tries for sides of these sizes, saturating at `u64::MAX`.
+fn orders(n_attackers: usize, n_defenders: usize) -> u64 {
+    (1..=n_attackers as u64)
+        .chain(1..=n_defenders as u64)
+        .fold(1, u64::saturating_mul)
+}
+
+fn optimize(body: &str) -> Result<Value, Response> {
+    let (attackers, defenders) = parse_scenario(body)?;
+    if orders(attackers.len(), defenders.len()) > MAX_ORDERS {
+        return Err(Response::error(
+            413,
+            format!("too many units to try every order, at most {MAX_ORDERS} orders are allowed"),
+        ));
+    }
+    if option(body, "pareto") {
+        let front = pareto_front(attackers, defenders);
+        return Ok(json!({ "plans": front.iter().map(plan_json).collect::<Vec<_>>() }));
+    }
+    if attackers.is_empty() || defenders.is_empty() {
+        return Ok(log_json(0, &CombatLog::new()));
+    }
+    let (score, log) = optimized(attackers, defenders);
+
+    Ok(log_json(score, &log))
+}
+
+fn bulk(body: &str) -> Result<Value, Response> {
+    let (attacker, defender) = parse_duel(body)?;
+    let report = bulk_copies(&attacker, defender).map_err(|err| Response::error(422, err))?;
+
+    let hits: Vec<Value> = report
+        .hits
+        .iter()
+        .map(|hit| {
+            json!({
+                "attacker_hp": hit.attacker_hp,
+                "defender_hp": hit.defender_hp,
+                "damage_dealt": hit.damage_dealt,
+                "damage_received": hit.damage_received,
+            })
+        })
+        .collect();
+    Ok(json!({
+        "attackers": hits.len(),
+        "attackers_lost": report.attackers_lost(),
+        "killed": report.killed,
+        "hits": hits,
+    }))
+}
+
+fn eliminate(body: &str) -> Result<Value, Response> {
+    let (attacker, defender) = parse_duel(body)?;
+    let result = |result: Result<_, _>| match result {
+        Ok(hp) => json!({ "hp": hp }),
+        Err(err) => json!({ "error": format!("{err}") }),
+    };
+
+    Ok(json!({
+        "defender": result(eliminate_defender(&attacker, defender.clone())),
+        "attacker": result(eliminate_attacker(attacker, &defender)),
+    }))
+}
+
+/// Answers one request.
+pub fn handle(method: &str, path: &str, body: &str) -> Response {
+    let endpoint: fn(&str) -> Result<Value, Response> = match path {
+        "/openapi.json" if method == "GET" => {
+            return Response {
+                status: 200,
+                body: OPENAPI.to_owned(),
+            };
+        }
+        "/single" => single,
+        "/multi" => multi,
+        "/optimize" => optimize,
+        "/bulk" => bulk,
+        "/eliminate" => eliminate,
+        "/openapi.json" => return Response::error(405, "use GET"),
+        _ => return Response::error(404, format!("no endpoint at `{path}`")),
+    };
+    if method != "POST" {
+        return Response::error(405, "use POST");
+    }
+    if body.len() > MAX_BODY_BYTES {
+        return Response::error(413, format!("bodies are limited to {MAX_BODY_BYTES} bytes"));
+    }
+
+    endpoint(body).map_or_else(|err| err, Response::ok)
+}
+
+/// A connection that must be done with by a fixed time. Every read and write only waits for
+/// what is left of it, so a client trickling bytes cannot hold its connection open.
+struct Deadline {
+    stream: TcpStream,
+    until: Instant,
+}
+
+impl Deadline {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            until: Instant::now() + IO_TIMEOUT,
+        }
+    }
+
+    fn remaining(&self) -> std::io::Result<Duration> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(std::io::Error::new(
+                ErrorKind::TimedOut,
+                "the request took too long",
+            ));
+        }
+        Ok(left)
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Deadline {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Reads the next line of the request head into `line`. Returns false if the line runs past
+/// the end of `head`.
+fn read_head_line<R: BufRead>(head: &mut Take<R>, line: &mut String) -> std::io::Result<bool> {
+    line.clear();
+    head.read_line(line)?;
+    Ok(line.ends_with('\n') || head.limit() > 0)
+}
+
+/// Reads one request from `stream`, answers it and closes the connection. Sending the
+/// request and reading the response each have to be over within [`IO_TIMEOUT`].
+fn handle_connection(stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(Deadline::new(stream.try_clone()?));
+    let too_large = || Response::error(413, "headers too large");
+
+    // The request line and headers are read through one limit, so a line that never ends
+    // cannot grow without bound.
+    let mut head = reader.by_ref().take(MAX_HEADER_BYTES as u64);
+    let mut request_line = String::new();
+    if !read_head_line(&mut head, &mut request_line)? {
+        return respond(Deadline::new(stream), too_large());
+    }
+    let mut content_length = 0;
+    let mut line = String::new();
+    loop {
+        if !read_head_line(&mut head, &mut line)? {
+            return respond(Deadline::new(stream), too_large());
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(usize::MAX);
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    // Query strings are not used.
+    let path = path.split('?').next().unwrap_or(path);
+
+    let response = if content_length > MAX_BODY_BYTES {
+        Response::error(413, format!("bodies are limited to {MAX_BODY_BYTES} bytes"))
+    } else {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        match String::from_utf8(body) {
+            Ok(body) => handle(method, path, &body),
+            Err(_) => Response::error(400, "the body is not UTF-8"),
+        }
+    };
+
+    respond(Deadline::new(stream), response)
+}
+
+fn respond(mut writer: impl Write, response: Response) -> std::io::Result<()> {
+    let message = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.body.len(),
+        response.body,
+    );
+    writer.write_all(message.as_bytes())?;
+    writer.flush()
+}
+
+/// Holds one of the [`MAX_CONNECTIONS`] slots until dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(Arc::clone(active)))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// `serve [--addr HOST:PORT]`
+///
+/// Serves until killed, on `127.0.0.1:8080` by default. Each connection gets its own thread,
+/// so a long optimization does not hold up other requests, up to [`MAX_CONNECTIONS`] at
+/// once.
+pub fn serve(args: &[String]) -> Result<String, String> {
+    let addr = match args {
+        [] => "127.0.0.1:8080",
+        [flag, addr] if flag == "--addr" => addr,
+        _ => return Err("usage: serve [--addr HOST:PORT]".to_owned()),
+    };
+
+    let listener = TcpListener::bind(addr).map_err(|err| format!("cannot bind {addr}: {err}"))?;
+    eprintln!("listening on http://{addr}");
+
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let Some(slot) = Slot::take(&active) else {
+            // Best effort: the short answer fits in the socket buffer, and a peer that is not
+            // reading must not hold up the accept loop.
+            let busy = Response::error(503, "too many connections, try again later");
+            let _ = stream
+                .set_nonblocking(true)
+                .and_then(|()| respond(&stream, busy));
+            continue;
+        };
+        std::thread::spawn(move || {
+            let _slot = slot;
+            if let Err(err) = handle_connection(stream) {
+                eprintln!("error: {err}");
+            }
+        });
+    }
+
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(response: &Response) -> Value {
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_shorthand_and_json_agree() {
+        let shorthand = handle("POST", "/multi", "wa, ca / de 10");
+        let json = handle(
+            "POST",
+            "/multi",
+            r#"{"attackers": ["wa", {"type": "Catapult"}], "defenders": [{"type": "de", "hp": 10}]}"#,
+        );
+
+        assert_eq!(shorthand.status, 200);
+        assert_eq!(shorthand.body, json.body);
+        assert_eq!(body(&shorthand)["events"][0]["kind"], "Attack");
+    }
+
+    #[test]
+    fn test_duel_endpoints() {
+        let single = body(&handle("POST", "/single", "wa / wa"));
+        assert_eq!(single["damage_to_defender"], 5);
+
+        let eliminate = body(&handle("POST", "/eliminate", r#"{"battle": "wa / gi"}"#));
+        assert!(eliminate["defender"]["hp"].is_u64());
+
+        let bulk = handle("POST", "/bulk", "eg / wa");
+        assert_eq!(bulk.status, 422);
+        assert_eq!(handle("POST", "/bulk", "wa, wa / wa").status, 400);
+    }
+
+    #[test]
+    fn test_optimize_pareto() {
+        let battle = "ca 2, wa, wa 3 / wa 8, ar 8";
+        let best = body(&handle("POST", "/optimize", battle));
+        let front = body(&handle(
+            "POST",
+            "/optimize",
+            &format!(r#"{{"battle": "{battle}", "pareto": true}}"#),
+        ));
+
+        assert!(best["score"].is_i64());
+        let plans = front["plans"].as_array().unwrap();
+        assert_eq!(plans.len(), 3);
+        assert_eq!(plans[0]["kills"], 2);
+        assert_eq!(plans[2]["attackers_lost"], 0);
+    }
+
+    #[test]
+    fn test_request_line_too_long() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let server = std::thread::spawn(move || handle_connection(stream));
+
+        // The line never ends, so only the header limit stops the server from reading on.
+        // Sending no more than the limit leaves nothing unread to reset the connection.
+        client.write_all(&vec![b'G'; MAX_HEADER_BYTES]).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 413 "));
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_limits_and_routing() {
+        let crowd = vec!["\"wa\""; MAX_UNITS + 1].join(",");
+        let scenario = format!(r#"{{"attackers": [{crowd}], "defenders": ["wa"]}}"#);
+        assert_eq!(handle("POST", "/optimize", &scenario).status, 413);
+        // Twelve units a side fit in a body, but not in the order limit.
+        let full = ["wa"; MAX_UNITS].join(",");
+        assert_eq!(
+            handle("POST", "/optimize", &format!("{full} / wa")).status,
+            413
+        );
+        assert_eq!(
+            handle("POST", "/multi", &format!("{full} / wa")).status,
+            200
+        );
+        assert!(orders(9, 1) <= MAX_ORDERS && orders(10, 1) > MAX_ORDERS);
+        assert_eq!(
+            handle("POST", "/multi", &" ".repeat(MAX_BODY_BYTES + 1)).status,
+            413
+        );
+
+        assert_eq!(handle("GET", "/multi", "").status, 405);
+        assert_eq!(handle("POST", "/nope", "").status, 404);
+        let openapi: Value =
+            serde_json::from_str(&handle("GET", "/openapi.json", "").body).unwrap();
+        assert!(openapi["paths"]["/optimize"].is_object());
+    }
+}
@@ -0,0 +1,474 @@
+use std::fmt;
+
+use crate::{
+    combat::{CombatLog, multi_combat_outcome, optimized_order, turn_start_skills},
+    unit::{Hp, Skills, StatusEffects, Unit, Units},
+};
+
+/// Hp a unit recovers when it rests inside its own territory.
+const HEAL_IN_TERRITORY: Hp = 4;
+/// Hp a unit recovers when it rests outside its own territory.
+const HEAL_OUT_OF_TERRITORY: Hp = 2;
+/// Hp a Mind Bender restores to the units around it.
+const MIND_BENDER_HEAL: Hp = 4;
+/// Hp an auto-healing unit restores to itself and the units around it at the start of its
+/// side's turn, without using its action.
+const AUTO_HEAL: Hp = 4;
+
+/// Rules for [`siege`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SiegeOptions {
+    /// The siege is given up after this many turns.
+    pub max_turns: u8,
+    pub attackers_in_territory: bool,
+    pub defenders_in_territory: bool,
+    /// Defenders that do not need to heal fortify, e.g. because they are in a city.
+    pub defenders_fortify: bool,
+    /// Defenders make their best volley on the attackers in their turn. Without it they only
+    /// rest and heal.
+    pub defenders_attack: bool,
+}
+
+impl Default for SiegeOptions {
+    fn default() -> Self {
+        Self {
+            max_turns: 10,
+            attackers_in_territory: false,
+            defenders_in_territory: true,
+            defenders_fortify: true,
+            defenders_attack: true,
+        }
+    }
+}
+
+/// What happened in one turn of a siege and where both sides stand at the end of it.
+#[derive(Debug)]
+pub struct Turn {
+    pub turn: u8,
+    pub log: CombatLog,
+    pub attackers: Units,
+    pub defenders: Units,
+}
+
+#[derive(Debug)]
+pub struct SiegeReport {
+    pub turns: Vec<Turn>,
+    /// The turn on which the last defender was killed, if it was.
+    pub captured_on: Option<u8>,
+}
+
+fn heals_others(unit: &Unit) -> bool {
+    unit.skills.contains(Skills::HEAL)
+}
+
+fn auto_heals(unit: &Unit) -> bool {
+    unit.skills.contains(Skills::AUTO_HEAL)
+}
+
+fn boosts(unit: &Unit) -> bool {
+    unit.skills.contains(Skills::BOOST) && !unit.status_effects.contains(StatusEffects::FROZEN)
+}
+
+/// Whether `a` and `b` are next to each other. Units without positions all count as being
+/// around each other.
+fn around(a: &Unit, b: &Unit) -> bool {
+    match (a.position, b.position) {
+        (Some(a), Some(b)) => a.is_adjacent(b),
+        _ => true,
+    }
+}
+
+/// Heals every unit around `healer` by `amount`.
+fn heal_around(units: &mut Units, healer: usize, amount: Hp, include_self: bool) {
+    let healer_unit = units[healer].clone();
+
+    for (i, unit) in units.iter_mut().enumerate() {
+        let in_reach = if i == healer {
+            include_self
+        } else {
+            around(&healer_unit, unit)
+        };
+        if unit.current_hp > 0 && in_reach {
+            unit.heal(amount);
+        }
+    }
+}
+
+/// Boosts every other unit around `booster` that is not boosted yet.
+fn boost_around(units: &mut Units, booster: usize) {
+    let booster_unit = units[booster].clone();
+
+    for (i, unit) in units.iter_mut().enumerate() {
+        if i != booster
+            && unit.current_hp > 0
+            && !unit.status_effects.contains(StatusEffects::BOOSTED)
+            && around(&booster_unit, unit)
+        {
+            unit.apply_status_effects(StatusEffects::BOOSTED);
+        }
+    }
+}
+
+/// Gets a side ready for its turn: growing units age, auto-healing units heal their
+/// surroundings and units with enough kills are promoted.
+fn start_turn(units: &mut Units) {
+    for unit in units.iter_mut() {
+        unit.grow_older();
+    }
+
+    for i in 0..units.len() {
+        if auto_heals(&units[i]) {
+            heal_around(units, i, AUTO_HEAL, true);
+        }
+    }
+
+    for unit in units.iter_mut() {
+        if unit.can_promote() {
+            unit.promote();
+        }
+    }
+}
+
+/// Spends the action of a unit that is not attacking. Frozen units thaw, poisoned units
+/// cure the poison, and everything else recovers hp.
+fn rest(unit: &mut Unit, in_territory: bool) {
+    if unit.status_effects.contains(StatusEffects::FROZEN) {
+        unit.remove_status_effects(StatusEffects::FROZEN);
+    } else if unit.status_effects.contains(StatusEffects::POISONED) {
+        unit.remove_status_effects(StatusEffects::POISONED);
+    } else if in_territory {
+        unit.heal(HEAL_IN_TERRITORY);
+    } else {
+        unit.heal(HEAL_OUT_OF_TERRITORY);
+    }
+}
+
+/// Plays the turn of the side `attackers`: boosters boost, the fighters make their best
+/// volley on `defenders`, idle units rest and healers heal.
+fn volley_turn(
+    mut attackers: Units,
+    defenders: Units,
+    in_territory: bool,
+) -> (CombatLog, Units, Units) {
+    // Boosters spend their action boosting the units around them before the volley.
+    for i in 0..attackers.len() {
+        if boosts(&attackers[i]) {
+            boost_around(&mut attackers, i);
+        }
+    }
+
+    let (fighters, mut supporters): (Vec<Unit>, Vec<Unit>) =
+        attackers.into_iter().partition(|unit| {
+            unit.attack > 0 && !boosts(unit) && !unit.status_effects.contains(StatusEffects::FROZEN)
+        });
+
+    // Eggs and other units that cannot attack may leave nobody to order.
+    let fighters: Units = fighters.into_iter().collect();
+    let (order, defender_order) = if fighters.is_empty() || defenders.is_empty() {
+        (fighters, defenders)
+    } else {
+        let (_, order, defender_order) = optimized_order(fighters, defenders);
+        (order, defender_order)
+    };
+    let outcome = multi_combat_outcome(&order, defender_order);
+
+    let mut attackers = outcome.attackers;
+    for (unit, acted) in attackers.iter_mut().zip(&outcome.acted) {
+        if *acted {
+            unit.remove_status_effects(StatusEffects::BOOSTED);
+        } else if unit.current_hp > 0 {
+            rest(unit, in_territory);
+        }
+    }
+    // Severed segments die without acting and must not rest back to life.
+    attackers.retain_alive();
+
+    for unit in supporters
+        .iter_mut()
+        .filter(|unit| !heals_others(unit) && !boosts(unit))
+    {
+        rest(unit, in_territory);
+    }
+    attackers.extend(supporters);
+    attackers.extend(outcome.converted);
+
+    for i in 0..attackers.len() {
+        if heals_others(&attackers[i])
+            && !attackers[i].status_effects.contains(StatusEffects::FROZEN)
+        {
+            heal_around(&mut attackers, i, MIND_BENDER_HEAL, false);
+        }
+    }
+
+    (outcome.log, attackers, outcome.defenders)
+}
+
+/// Plays the defenders' turn: a volley on the attackers if they attack, or resting and
+/// healing if they do not, followed by fortifying.
+fn defenders_turn(
+    mut defenders: Units,
+    mut attackers: Units,
+    options: &SiegeOptions,
+) -> (CombatLog, Units, Units) {
+    let mut log = CombatLog::new();
+    if options.defenders_attack {
+        (log, defenders, attackers) =
+            volley_turn(defenders, attackers, options.defenders_in_territory);
+    } else {
+        for i in 0..defenders.len() {
+            if heals_others(&defenders[i]) {
+                heal_around(&mut defenders, i, MIND_BENDER_HEAL, false);
+            }
+        }
+        for unit in defenders.iter_mut() {
+            rest(unit, options.defenders_in_territory);
+        }
+    }
+
+    if options.defenders_fortify {
+        for unit in defenders.iter_mut() {
+            if !unit.status_effects.contains(StatusEffects::WALLED) {
+                unit.apply_status_effects(StatusEffects::FORTIFIED);
+            }
+        }
+    }
+
+    (log, defenders, attackers)
+}
+
+/// Simulates a siege turn by turn until every defender is dead, every attacker is dead, or
+/// `options.max_turns` have passed.
+///
+/// Each side's turn starts with eggs and baby dragons growing, see [`Unit::grow_older`], and
+/// with the skills of its units that act on their own, like tentacles striking and freeze
+/// areas freezing the enemies next to them. The attackers then make their best volley after
+/// boosters have boosted them, with idle attackers resting, healers healing and converted
+/// defenders joining the attackers. The defenders then take the same kind of turn against
+/// the attackers, unless `options.defenders_attack` is off, and fortify.
+pub fn siege(mut attackers: Units, mut defenders: Units, options: SiegeOptions) -> SiegeReport {
+    let mut turns = Vec::new();
+    let mut captured_on = None;
+
+    for turn in 1..=options.max_turns {
+        start_turn(&mut attackers);
+        let mut log = turn_start_skills(&attackers, &mut defenders);
+        defenders.retain_alive();
+
+        let volley;
+        (volley, attackers, defenders) = if attackers.is_empty() || defenders.is_empty() {
+            (CombatLog::new(), attackers, defenders)
+        } else {
+            volley_turn(attackers, defenders, options.attackers_in_territory)
+        };
+        log.extend(volley);
+        attackers.retain_alive();
+        defenders.retain_alive();
+
+        if !defenders.is_empty() {
+            start_turn(&mut defenders);
+            log.extend(turn_start_skills(&defenders, &mut attackers));
+            attackers.retain_alive();
+
+            let volley;
+            (volley, defenders, attackers) = defenders_turn(defenders, attackers, &options);
+            log.extend(volley);
+            attackers.retain_alive();
+            defenders.retain_alive();
+        }
+
+        turns.push(Turn {
+            turn,
+            log,
+            attackers: attackers.clone(),
+            defenders: defenders.clone(),
+        });
+
+        if defenders.is_empty() {
+            captured_on = Some(turn);
+            break;
+        }
+        if attackers
+            .alive()
+            .all(|unit| unit.attack == 0 && unit.unit_type.grows_into().is_none())
+        {
+            break;
+        }
+    }
+
+    SiegeReport { turns, captured_on }
+}
+
+fn write_units(f: &mut fmt::Formatter<'_>, units: &Units) -> fmt::Result {
+    for (i, unit) in units.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{:#} {}", unit.unit_type, unit.current_hp)?;
+        if !unit.status_effects.is_empty() {
+            write!(f, " {:#}", unit.status_effects)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for SiegeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for turn in &self.turns {
+            write!(
+                f,
+                "Turn {}: {} attacks\n  attackers: ",
+                turn.turn,
+                turn.log.len()
+            )?;
+            write_units(f, &turn.attackers)?;
+            f.write_str("\n  defenders: ")?;
+            write_units(f, &turn.defenders)?;
+            f.write_str("\n")?;
+        }
+
+        match self.captured_on {
+            Some(turn) => write!(f, "Captured on turn {turn}"),
+            None => write!(f, "Not captured"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Position,
+        unit::{TURNS_TO_GROW, UnitType},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_siege_wa_wa_vs_de_d() {
+        let attackers = Units::from([Unit::new(UnitType::Warrior), Unit::new(UnitType::Warrior)]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Defender).with_status_effects(StatusEffects::FORTIFIED)
+        ]);
+
+        let report = siege(
+            attackers.clone(),
+            defenders.clone(),
+            SiegeOptions::default(),
+        );
+
+        // Both warriors hit for 3 and take 9 back, then the defender finishes one off. The
+        // last warrior dies to retaliation on turn 2.
+        assert_eq!(report.captured_on, None);
+        assert_eq!(report.turns.len(), 2);
+        let first = &report.turns[0];
+        assert_eq!(first.log.len(), 3);
+        assert_eq!(first.log[2].attacker.unit_type, UnitType::Defender);
+        assert_eq!(first.attackers.len(), 1);
+        assert_eq!(first.attackers[0].current_hp, 1);
+        assert_eq!(first.defenders[0].current_hp, 9);
+        assert!(report.turns[1].attackers.is_empty());
+
+        let passive = siege(
+            attackers,
+            defenders,
+            SiegeOptions {
+                defenders_attack: false,
+                ..SiegeOptions::default()
+            },
+        );
+        // The defender only rests, recovering 4 hp in its own territory.
+        let first = &passive.turns[0];
+        assert_eq!(first.log.len(), 2);
+        assert_eq!(first.attackers.len(), 2);
+        assert_eq!(first.defenders[0].current_hp, 13);
+    }
+
+    #[test]
+    fn test_siege_captures() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Knight),
+            Unit::new(UnitType::Swordsman),
+            Unit::new(UnitType::Catapult),
+            Unit::new(UnitType::MindBender),
+        ]);
+        let defenders = Units::from([Unit::new(UnitType::Warrior)]);
+
+        let report = siege(attackers, defenders, SiegeOptions::default());
+
+        assert_eq!(report.captured_on, Some(1));
+        assert!(report.to_string().ends_with("Captured on turn 1"));
+    }
+
+    #[test]
+    fn test_rest_cures_poison_before_healing() {
+        let mut unit = Unit::new(UnitType::Warrior)
+            .with_current_hp(5)
+            .with_status_effects(StatusEffects::POISONED);
+
+        rest(&mut unit, true);
+        assert_eq!(unit.current_hp, 5);
+        assert!(unit.status_effects.is_empty());
+
+        rest(&mut unit, true);
+        assert_eq!(unit.current_hp, 9);
+    }
+
+    #[test]
+    fn test_severed_segment_stays_dead() {
+        // The head dies to the giant's retaliation, out of its segment's reach.
+        let attackers = Units::from([
+            Unit::new(UnitType::Centipede)
+                .with_current_hp(1)
+                .with_body(0)
+                .with_position(Position::new(0, 0)),
+            Unit::new(UnitType::Segment)
+                .with_body(0)
+                .with_position(Position::new(5, 5)),
+        ]);
+        let defenders =
+            Units::from([Unit::new(UnitType::Giant).with_position(Position::new(1, 0))]);
+
+        let report = siege(attackers, defenders, SiegeOptions::default());
+
+        assert_eq!(report.captured_on, None);
+        assert_eq!(report.turns.len(), 1);
+        assert!(report.turns[0].attackers.is_empty());
+    }
+
+    #[test]
+    fn test_promotion() {
+        let mut units = Units::from([Unit::new(UnitType::Rider).with_current_hp(3)]);
+        units[0].kills = 3;
+
+        start_turn(&mut units);
+
+        assert!(units[0].status_effects.contains(StatusEffects::VETERAN));
+        assert_eq!(units[0].current_hp, 15);
+    }
+
+    #[test]
+    fn test_eggs_hatch_during_siege() {
+        // Out of reach of each other, so the dragon just grows.
+        let attackers = Units::from([Unit::new(UnitType::Egg).with_position(Position::new(0, 0))]);
+        let defenders =
+            Units::from([Unit::new(UnitType::Giant).with_position(Position::new(5, 5))]);
+
+        let report = siege(
+            attackers,
+            defenders,
+            SiegeOptions {
+                max_turns: 2 * TURNS_TO_GROW,
+                ..SiegeOptions::default()
+            },
+        );
+
+        let stages: Vec<_> = report
+            .turns
+            .iter()
+            .map(|turn| turn.attackers[0].unit_type)
+            .collect();
+        assert_eq!(stages[usize::from(TURNS_TO_GROW) - 2], UnitType::Egg);
+        assert_eq!(stages[usize::from(TURNS_TO_GROW) - 1], UnitType::BabyDragon);
+        assert_eq!(stages.last(), Some(&UnitType::FireDragon));
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+
+use crate::{
+    combat::{CombatLog, EventKind, multi_combat_log, optimized_by},
+    unit::{Hp, STAT_SCALE, StatusEffects, Unit, Units},
+};
+
+/// The star value of a fight from the attackers' side.
+///
+/// Values are in tenths of a star, see [`STAT_SCALE`], since damage takes away part of a
+/// unit's value: a hit that takes a third of a 6 star unit's max hp is worth 2 stars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trade {
+    /// Full cost of the enemies killed or converted.
+    pub destroyed: i32,
+    /// Full cost of the attackers killed.
+    pub lost: i32,
+    /// Value of the hp taken off the enemies, kills included.
+    pub dealt: i32,
+    /// Value of the hp the attackers lost, deaths included.
+    pub taken: i32,
+}
+
+/// The value of `damage` hp taken off `unit`, in tenths of a star.
+fn hp_value(unit: &Unit, damage: Hp) -> i32 {
+    let scale = STAT_SCALE as u32;
+    let max_hp = u32::from(unit.max_hp.max(1));
+    let value = unit.cost() * scale * u32::from(damage.min(unit.current_hp));
+
+    ((2 * value + max_hp) / (2 * max_hp)) as i32
+}
+
+fn cost(unit: &Unit) -> i32 {
+    (unit.cost() * STAT_SCALE as u32) as i32
+}
+
+impl Trade {
+    /// Values every event in a volley's log. Tentacle strikes and lost segments count
+    /// against the attackers, every other strike against the defenders.
+    pub fn of(log: &CombatLog) -> Self {
+        let mut trade = Trade::default();
+
+        for event in log.iter() {
+            match event.kind {
+                EventKind::Freeze | EventKind::Grow => {}
+                EventKind::Tentacles | EventKind::SegmentLost => {
+                    trade.taken += hp_value(&event.defender, event.damage_to_defender);
+                    if event.is_kill() {
+                        trade.lost += cost(&event.defender);
+                    }
+                }
+                _ => {
+                    trade.dealt += hp_value(&event.defender, event.damage_to_defender);
+                    trade.taken += hp_value(&event.attacker, event.damage_to_attacker);
+
+                    let converted = event
+                        .status_effects_to_defender
+                        .contains(StatusEffects::CONVERTED);
+                    if event.is_kill() || converted {
+                        trade.destroyed += cost(&event.defender);
+                    }
+                    if converted && !event.is_kill() {
+                        // A converted unit is taken whole, not just the hp the hit removed.
+                        trade.dealt += cost(&event.defender)
+                            - hp_value(&event.defender, event.damage_to_defender);
+                    }
+                    if event.damage_to_attacker >= event.attacker.current_hp {
+                        trade.lost += cost(&event.attacker);
+                    }
+                }
+            }
+        }
+
+        trade
+    }
+
+    /// The value the attackers took off the defenders minus the value they lost, counting
+    /// partly damaged units by the share of their hp that is gone.
+    pub fn net(&self) -> i32 {
+        self.dealt - self.taken
+    }
+}
+
+fn write_stars(f: &mut fmt::Formatter<'_>, tenths: i32) -> fmt::Result {
+    let scale = STAT_SCALE as i32;
+    let sign = if tenths < 0 { "-" } else { "" };
+    write!(f, "{sign}{}.{}", tenths.abs() / scale, tenths.abs() % scale)
+}
+
+impl fmt::Display for Trade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            ("Destroyed", self.destroyed),
+            ("Lost", self.lost),
+            ("Hp value dealt", self.dealt),
+            ("Hp value taken", self.taken),
+            ("Net trade", self.net()),
+        ];
+
+        for (i, (label, tenths)) in rows.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{label}: ")?;
+            write_stars(f, tenths)?;
+            f.write_str(" stars")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Scores a volley by its [`Trade::net`], for use with [`optimized_by`].
+pub fn trade_score(attackers: &Units, defenders: Units) -> i32 {
+    Trade::of(&multi_combat_log(attackers, defenders)).net()
+}
+
+/// Finds the attack order with the best star trade, see [`trade_score`].
+pub fn optimized_for_trade(attackers: Units, defenders: Units) -> (i32, CombatLog) {
+    optimized_by(attackers, defenders, trade_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::UnitType;
+
+    use super::*;
+
+    #[test]
+    fn test_trade_kn_vs_wa() {
+        let attackers = Units::from([Unit::new(UnitType::Knight)]);
+        let defenders = Units::from([Unit::new(UnitType::Warrior).with_current_hp(5)]);
+
+        let trade = Trade::of(&multi_combat_log(&attackers, defenders));
+
+        // The warrior was already at half hp, so killing it takes half its value.
+        assert_eq!(trade.destroyed, 20);
+        assert_eq!(trade.dealt, 10);
+        assert_eq!(trade.lost, 0);
+        assert_eq!(trade.net(), 10);
+        assert!(trade.to_string().ends_with("Net trade: 1.0 stars"));
+    }
+
+    #[test]
+    fn test_optimized_for_trade_matches_report() {
+        let attackers = Units::from([
+            Unit::new(UnitType::Warrior),
+            Unit::new(UnitType::Catapult),
+            Unit::new(UnitType::Rider),
+        ]);
+        let defenders = Units::from([
+            Unit::new(UnitType::Defender),
+            Unit::new(UnitType::Archer).with_current_hp(4),
+        ]);
+
+        let (score, log) = optimized_for_trade(attackers, defenders);
+
+        assert_eq!(Trade::of(&log).net(), score);
+    }
+}
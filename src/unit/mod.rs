@@ -1,12 +1,39 @@
 use std::ops::Index;
 
 use arrayvec::ArrayVec;
-use generated::UNIT_TYPE_DATA;
-use strum::IntoStaticStr;
+use generated::{UNIT_TYPE_DATA, UNIT_TYPE_METADATA};
+
+use crate::board::Position;
+use strum::{IntoStaticStr, VariantArray};
 
 mod generated;
+mod names;
+mod shorthand;
+mod status;
+mod tribe;
+
+pub use names::ParseUnitTypeError;
+pub use shorthand::{ParseBattleError, parse_battle};
+pub use status::StatusEffectsError;
+pub use tribe::{ParseTribeError, Tribe};
+
+pub const MAX_UNITS: usize = 12;
+
+/// Hit points. Like the game, hp is only ever tracked and dealt in whole points.
+pub type Hp = u16;
+
+/// The in-game currency units are bought with.
+pub type Stars = u32;
 
-const MAX_UNITS: usize = 12;
+/// Attack, defense and defense bonuses are fixed-point numbers with this many steps per
+/// point, so an `attack` of `25` is 2.5 attack and a `defense_bonus` of `15` is 1.5x.
+pub const STAT_SCALE: u64 = 10;
+
+/// Kills a unit needs before it can be promoted to veteran.
+pub const KILLS_TO_PROMOTE: u8 = 3;
+/// Turns a growing unit spends in one stage before growing into the next, see
+/// [`UnitType::grows_into`].
+pub const TURNS_TO_GROW: u8 = 3;
 
 bitflags::bitflags! {
     /// A unit status effect.
@@ -16,7 +43,8 @@ bitflags::bitflags! {
         const BOOSTED = 0b00000001;
         /// The unit is converted to the enemy's side.
         const CONVERTED = 0b00000010;
-        /// The unit deals explosion damage to adjacent enemies.
+        /// The unit explodes instead of attacking, dealing explosion damage to adjacent
+        /// enemies. Only units with [`Skills::EXPLODE`] can explode.
         const EXPLODING = 0b00000100;
         /// The unit's defense is increased by 50%.
         const FORTIFIED = 0b00001000;
@@ -35,7 +63,78 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, IntoStaticStr)]
+bitflags::bitflags! {
+    /// Abilities a unit type always has, loaded from `unit_data.yaml`.
+    #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+    pub struct Skills: u16 {
+        /// Attacks also damage every enemy next to the attacker.
+        const STOMP = 0b00000001;
+        /// Attacks also deal half damage to every enemy next to the target.
+        const SPLASH = 0b00000010;
+        /// Strikes every enemy next to the unit at the start of its turn and every enemy that
+        /// moves next to it.
+        const TENTACLES = 0b00000100;
+        /// The unit can explode instead of attacking, see [`StatusEffects::EXPLODING`].
+        const EXPLODE = 0b00001000;
+        /// The unit can attack again after a kill.
+        const PERSIST = 0b00010000;
+        /// Attacks convert the target to the attacker's side.
+        const CONVERT = 0b00100000;
+        /// The unit heals the friendly units next to it instead of attacking.
+        const HEAL = 0b01000000;
+        /// The unit heals itself and the friendly units next to it at the start of its turn.
+        const AUTO_HEAL = 0b10000000;
+        /// The unit boosts the friendly units next to it instead of attacking.
+        const BOOST = 0b00000001_00000000;
+        /// Attacks freeze the target.
+        const FREEZE = 0b00000010_00000000;
+        /// Freezes every enemy next to the unit at the start of its turn.
+        const FREEZE_AREA = 0b00000100_00000000;
+        /// Attacks poison the target.
+        const POISON = 0b00001000_00000000;
+        /// Kills grow a new segment onto the unit's body, see [`Unit::body`].
+        const GROW = 0b00010000_00000000;
+    }
+}
+
+impl Skills {
+    /// The status effects attacks by a unit with these skills apply to their target.
+    pub fn inflicted(self) -> StatusEffects {
+        [
+            (Skills::CONVERT, StatusEffects::CONVERTED),
+            (Skills::FREEZE, StatusEffects::FROZEN),
+            (Skills::POISON, StatusEffects::POISONED),
+        ]
+        .into_iter()
+        .filter(|&(skill, _)| self.contains(skill))
+        .map(|(_, effect)| effect)
+        .collect()
+    }
+}
+
+/// The broad kind of a unit.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, IntoStaticStr, VariantArray)]
+pub enum UnitClass {
+    Land,
+    /// Boats and units that live in water.
+    Naval,
+    /// The strongest unit of a tribe, trained in a city with a high enough level.
+    Super,
+    /// Units that cannot be trained, but spawn from other units or events.
+    Special,
+}
+
+/// What a unit type is besides its stats, loaded from `unit_data.yaml`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UnitMetadata {
+    pub tribe: Tribe,
+    pub cost: Stars,
+    pub class: UnitClass,
+    /// Whether the unit moves on water rather than on land.
+    pub water: bool,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, IntoStaticStr, VariantArray)]
 pub enum UnitType {
     Archer,
     ArcherBomber,
@@ -77,10 +176,6 @@ pub enum UnitType {
     DoomuxRammer,
     DoomuxScout,
     Egg,
-    EggBomber,
-    EggRaft,
-    EggRammer,
-    EggScout,
     Exida,
     ExidaBomber,
     ExidaRaft,
@@ -164,17 +259,63 @@ pub enum UnitType {
     WarriorScout,
 }
 
+impl UnitType {
+    pub(crate) fn metadata(self) -> &'static UnitMetadata {
+        &UNIT_TYPE_METADATA[self as usize]
+    }
+
+    /// Whether the unit moves on water rather than on land.
+    pub fn is_naval(self) -> bool {
+        self.metadata().water
+    }
+
+    pub fn class(self) -> UnitClass {
+        self.metadata().class
+    }
+
+    /// Stars it costs to train the unit. Naval variants also include the cost of upgrading
+    /// the boat.
+    pub fn cost(self) -> Stars {
+        self.metadata().cost
+    }
+
+    /// The unit type this one grows into after [`TURNS_TO_GROW`] turns, if any.
+    pub fn grows_into(self) -> Option<UnitType> {
+        match self {
+            UnitType::Egg => Some(UnitType::BabyDragon),
+            UnitType::BabyDragon => Some(UnitType::FireDragon),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Unit {
     pub unit_type: UnitType,
     pub range: u8,
     pub status_effects: StatusEffects,
-    pub trait_effects: StatusEffects,
-    pub current_hp: f32,
-    pub max_hp: f32,
-    pub attack: f32,
-    pub defense: f32,
-    pub defense_bonus: f32,
+    pub skills: Skills,
+    pub current_hp: Hp,
+    pub max_hp: Hp,
+    /// How many tiles the unit can move in a turn.
+    pub movement: u8,
+    /// Attack in tenths of a point, see [`STAT_SCALE`].
+    pub attack: u8,
+    /// Defense in tenths of a point, see [`STAT_SCALE`].
+    pub defense: u8,
+    /// Defense multiplier in tenths, see [`STAT_SCALE`].
+    pub defense_bonus: u8,
+    /// Whether the unit stands on terrain its tribe knows how to defend, see
+    /// [`Battlefield`](crate::battlefield::Battlefield).
+    pub terrain_bonus: bool,
+    /// Where the unit stands. Units without a position can reach any other unit.
+    pub position: Option<Position>,
+    /// Enemy units this unit has killed.
+    pub kills: u8,
+    /// The body a centipede head and its segments share. Segments die with their head.
+    pub body: Option<u8>,
+    /// Turns the unit has spent growing towards its next stage, see [`UnitType::grows_into`].
+    pub age: u8,
     pub retaliates: bool,
     pub surprise: bool,
 }
@@ -189,29 +330,144 @@ impl Unit {
         self
     }
 
-    pub fn with_current_hp(mut self, current_hp: f32) -> Self {
+    pub fn with_current_hp(mut self, current_hp: Hp) -> Self {
         self.current_hp = current_hp;
         self
     }
 
+    pub fn with_kills(mut self, kills: u8) -> Self {
+        self.kills = kills;
+        self
+    }
+
+    pub fn with_body(mut self, body: u8) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn with_position(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn with_terrain_bonus(mut self, terrain_bonus: bool) -> Self {
+        self.terrain_bonus = terrain_bonus;
+        self.update_defense_bonus();
+        self
+    }
+
     #[inline]
     pub fn apply_status_effects(&mut self, status_effects: StatusEffects) {
         if status_effects.contains(StatusEffects::VETERAN)
             && !self.status_effects.contains(StatusEffects::VETERAN)
         {
-            self.max_hp += 5.0;
+            self.max_hp += 5;
+        }
+
+        if status_effects.contains(StatusEffects::BOOSTED)
+            && !self.status_effects.contains(StatusEffects::BOOSTED)
+        {
+            self.attack += 5;
         }
 
         self.status_effects.insert(status_effects);
 
+        self.update_defense_bonus();
+    }
+
+    /// Removes status effects, e.g. when a unit heals off poison or thaws.
+    #[inline]
+    pub fn remove_status_effects(&mut self, status_effects: StatusEffects) {
+        if status_effects.contains(StatusEffects::BOOSTED)
+            && self.status_effects.contains(StatusEffects::BOOSTED)
+        {
+            self.attack -= 5;
+        }
+
+        self.status_effects.remove(status_effects);
+
+        self.update_defense_bonus();
+    }
+
+    /// Heals the unit by up to `amount`, without going over its max hp.
+    pub fn heal(&mut self, amount: Hp) {
+        self.current_hp = (self.current_hp + amount)
+            .min(self.max_hp)
+            .max(self.current_hp);
+    }
+
+    /// Whether the unit has enough kills to become a veteran and is not one yet.
+    pub fn can_promote(&self) -> bool {
+        self.kills >= KILLS_TO_PROMOTE && !self.status_effects.contains(StatusEffects::VETERAN)
+    }
+
+    /// Whether the unit has chosen to explode and can.
+    pub fn explodes(&self) -> bool {
+        self.skills.contains(Skills::EXPLODE)
+            && self.status_effects.contains(StatusEffects::EXPLODING)
+    }
+
+    /// Stars it costs to train the unit, see [`UnitType::cost`].
+    pub fn cost(&self) -> Stars {
+        self.unit_type.cost()
+    }
+
+    pub fn tribe(&self) -> Tribe {
+        self.unit_type.tribe()
+    }
+
+    pub fn class(&self) -> UnitClass {
+        self.unit_type.class()
+    }
+
+    /// Makes the unit a veteran, which also heals it fully.
+    pub fn promote(&mut self) {
+        self.apply_status_effects(StatusEffects::VETERAN);
+        self.current_hp = self.max_hp;
+    }
+
+    /// Ages the unit by a turn, growing it into its next stage once it has spent
+    /// [`TURNS_TO_GROW`] turns in this one. Returns whether it grew.
+    ///
+    /// Hp carries over in proportion to max hp, rounded half up. Status effects, kills,
+    /// position and terrain carry over as they are.
+    pub fn grow_older(&mut self) -> bool {
+        let Some(next) = self.unit_type.grows_into() else {
+            return false;
+        };
+
+        self.age += 1;
+        if self.age < TURNS_TO_GROW {
+            return false;
+        }
+
+        let mut grown = Unit::new(next)
+            .with_status_effects(self.status_effects)
+            .with_terrain_bonus(self.terrain_bonus)
+            .with_kills(self.kills);
+        grown.position = self.position;
+        grown.body = self.body;
+
+        let hp = u32::from(self.current_hp) * u32::from(grown.max_hp);
+        let max_hp = u32::from(self.max_hp);
+        grown.current_hp = Hp::try_from((2 * hp + max_hp) / (2 * max_hp)).unwrap_or(Hp::MAX);
+
+        *self = grown;
+        true
+    }
+
+    /// Recomputes `defense_bonus` from the status effects and terrain. The terrain bonus does
+    /// not stack with FORTIFIED, and POISONED removes every bonus.
+    #[inline]
+    fn update_defense_bonus(&mut self) {
         self.defense_bonus = if self.status_effects.contains(StatusEffects::POISONED) {
-            0.7
+            7
         } else if self.status_effects.contains(StatusEffects::WALLED) {
-            4.0
-        } else if self.status_effects.contains(StatusEffects::FORTIFIED) {
-            1.5
+            40
+        } else if self.status_effects.contains(StatusEffects::FORTIFIED) || self.terrain_bonus {
+            15
         } else {
-            1.0
+            10
         };
     }
 }
@@ -222,21 +478,135 @@ impl Default for Unit {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A fixed-capacity army of at most [`MAX_UNITS`] units.
+#[derive(Debug, Clone, Default)]
 pub struct Units(ArrayVec<Unit, MAX_UNITS>);
 
 impl Units {
     pub fn new() -> Self {
-        Self { 0: ArrayVec::new() }
+        Self(ArrayVec::new())
     }
 
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn swap(&mut self, a: usize, b: usize) {
         self.0.swap(a, b)
     }
+
+    /// Adds a unit to the end of the army.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the army already holds [`MAX_UNITS`] units.
+    pub fn push(&mut self, unit: Unit) {
+        self.0.push(unit)
+    }
+
+    /// Adds a unit to the end of the army, handing it back if the army is full.
+    pub fn try_push(&mut self, unit: Unit) -> Result<(), Unit> {
+        self.0.try_push(unit).map_err(|err| err.element())
+    }
+
+    /// Removes and returns the unit at `index`, shifting the following units down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Unit {
+        self.0.remove(index)
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&mut Unit) -> bool) {
+        self.0.retain(f)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Unit> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Unit> {
+        self.0.iter_mut()
+    }
+
+    /// Iterates over the units that still have hp left.
+    pub fn alive(&self) -> impl Iterator<Item = &Unit> {
+        self.0.iter().filter(|unit| unit.current_hp > 0)
+    }
+
+    /// Drops every unit that has no hp left.
+    pub fn retain_alive(&mut self) {
+        self.0.retain(|unit| unit.current_hp > 0)
+    }
+
+    /// Iterates over the indices of the units standing next to `position`.
+    pub fn adjacent_to(&self, position: Position) -> impl Iterator<Item = usize> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(move |(_, unit)| unit.position.is_some_and(|p| p.is_adjacent(position)))
+            .map(|(i, _)| i)
+    }
+
+    /// Grows a segment onto the body of the unit at `head`, giving the head a body of its own
+    /// first if it has none. The segment has no position yet. Returns the index of the new
+    /// segment, or `None` if the army is full or has run out of body ids.
+    pub fn grow(&mut self, head: usize) -> Option<usize> {
+        if self.0.is_full() {
+            return None;
+        }
+
+        let body = match self.0[head].body {
+            Some(body) => body,
+            None => {
+                let body = match self.0.iter().filter_map(|unit| unit.body).max() {
+                    Some(id) => id.checked_add(1)?,
+                    None => 0,
+                };
+                self.0[head].body = Some(body);
+                body
+            }
+        };
+
+        self.0.push(Unit::new(UnitType::Segment).with_body(body));
+        Some(self.0.len() - 1)
+    }
+
+    /// Kills the living segments sharing a body with the unit at `head`, returning their
+    /// indices and the hp they had.
+    pub fn sever(&mut self, head: usize) -> ArrayVec<(usize, Hp), MAX_UNITS> {
+        let mut severed = ArrayVec::new();
+        let Some(body) = self.0[head].body else {
+            return severed;
+        };
+        if !self.0[head].skills.contains(Skills::GROW) {
+            return severed;
+        }
+
+        for (i, unit) in self.0.iter_mut().enumerate() {
+            if i != head && unit.body == Some(body) && unit.current_hp > 0 {
+                severed.push((i, unit.current_hp));
+                unit.current_hp = 0;
+            }
+        }
+
+        severed
+    }
+
+    /// Sorts the units by current hp, lowest first. The sort is stable.
+    pub fn sort_by_hp(&mut self) {
+        self.0.sort_by_key(|unit| unit.current_hp)
+    }
+
+    /// Sorts the units by type in declaration order. The sort is stable.
+    pub fn sort_by_type(&mut self) {
+        self.0.sort_by_key(|unit| unit.unit_type as usize)
+    }
 }
 
 impl<const N: usize> From<[Unit; N]> for Units {
@@ -250,15 +620,49 @@ impl<const N: usize> From<[Unit; N]> for Units {
 }
 
 impl FromIterator<Unit> for Units {
+    /// Collects at most [`MAX_UNITS`] units, ignoring the rest.
     fn from_iter<I: IntoIterator<Item = Unit>>(iter: I) -> Self {
         let mut ret = Units::new();
-        for (i, unit) in iter.into_iter().take(MAX_UNITS).enumerate() {
-            ret.0[i] = unit;
-        }
+        ret.0.extend(iter.into_iter().take(MAX_UNITS));
         ret
     }
 }
 
+impl Extend<Unit> for Units {
+    /// Adds units until the army is full, ignoring the rest.
+    fn extend<I: IntoIterator<Item = Unit>>(&mut self, iter: I) {
+        let room = MAX_UNITS - self.0.len();
+        self.0.extend(iter.into_iter().take(room));
+    }
+}
+
+impl IntoIterator for Units {
+    type Item = Unit;
+    type IntoIter = arrayvec::IntoIter<Unit, MAX_UNITS>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Units {
+    type Item = &'a Unit;
+    type IntoIter = std::slice::Iter<'a, Unit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Units {
+    type Item = &'a mut Unit;
+    type IntoIter = std::slice::IterMut<'a, Unit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
 impl Index<usize> for Units {
     type Output = Unit;
 
@@ -272,3 +676,98 @@ impl std::ops::IndexMut<usize> for Units {
         &mut self.0[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_caps_at_max_units() {
+        let units: Units = std::iter::repeat_with(|| Unit::new(UnitType::Warrior))
+            .take(MAX_UNITS + 3)
+            .collect();
+
+        assert_eq!(units.len(), MAX_UNITS);
+        assert!(units.iter().all(|unit| unit.unit_type == UnitType::Warrior));
+    }
+
+    #[test]
+    fn test_try_push_full() {
+        let mut units: Units = std::iter::repeat_with(Unit::default)
+            .take(MAX_UNITS)
+            .collect();
+
+        let rejected = units.try_push(Unit::new(UnitType::Giant));
+
+        assert_eq!(
+            rejected.map_err(|unit| unit.unit_type),
+            Err(UnitType::Giant)
+        );
+    }
+
+    #[test]
+    fn test_sort_and_retain_alive() {
+        let mut units = Units::from([
+            Unit::new(UnitType::Knight).with_current_hp(7),
+            Unit::new(UnitType::Archer).with_current_hp(0),
+            Unit::new(UnitType::Giant).with_current_hp(3),
+        ]);
+
+        units.sort_by_hp();
+        let types: Vec<_> = units.iter().map(|unit| unit.unit_type).collect();
+        assert_eq!(types, [UnitType::Archer, UnitType::Giant, UnitType::Knight]);
+
+        units.retain_alive();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units.alive().count(), 2);
+
+        units.sort_by_type();
+        assert_eq!(units[0].unit_type, UnitType::Giant);
+    }
+
+    #[test]
+    fn test_grow_runs_out_of_bodies() {
+        let mut units = Units::from([
+            Unit::new(UnitType::Centipede).with_body(u8::MAX),
+            Unit::new(UnitType::Centipede),
+        ]);
+
+        assert_eq!(units.grow(0), Some(2));
+        assert_eq!(units[2].body, Some(u8::MAX));
+        assert_eq!(units.grow(1), None);
+        assert_eq!(units[1].body, None);
+    }
+
+    #[test]
+    fn test_grow_older_keeps_hp_proportion() {
+        let mut egg = Unit::new(UnitType::Egg).with_current_hp(5);
+
+        for _ in 1..TURNS_TO_GROW {
+            assert!(!egg.grow_older());
+        }
+        assert!(egg.grow_older());
+        assert_eq!(egg.unit_type, UnitType::BabyDragon);
+        assert_eq!(egg.current_hp, 8);
+        assert_eq!(egg.age, 0);
+
+        let mut fire_dragon = Unit::new(UnitType::FireDragon);
+        assert!(!fire_dragon.grow_older());
+        assert_eq!(fire_dragon.age, 0);
+    }
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(UnitType::Knight.cost(), 8);
+        assert_eq!(UnitType::WarriorBomber.cost(), 2 + 15);
+        assert_eq!(UnitType::Giant.class(), UnitClass::Super);
+        assert_eq!(UnitType::ArcherScout.class(), UnitClass::Naval);
+        assert_eq!(UnitType::Segment.class(), UnitClass::Special);
+
+        let mooni = Unit::new(UnitType::Mooni);
+        assert_eq!(mooni.tribe(), Tribe::Polaris);
+        assert_eq!(mooni.class(), UnitClass::Land);
+        assert!(UnitType::Crab.is_naval() && UnitType::Juggernaut.is_naval());
+        assert!(UnitType::Pirate.is_naval() && UnitType::ArcherRaft.is_naval());
+        assert!(!UnitType::Giant.is_naval() && !UnitType::Segment.is_naval());
+    }
+}
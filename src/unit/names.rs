@@ -0,0 +1,247 @@
+use std::fmt;
+use std::str::FromStr;
+
+use strum::VariantArray;
+
+use super::UnitType;
+
+/// Short code and extra aliases for every unit that is not a naval variant.
+///
+/// Short codes are the two-letter codes used in chat, e.g. `wa` or `kn`.
+const BASE_NAMES: &[(UnitType, &str, &[&str])] = &[
+    (UnitType::Archer, "ar", &["arch"]),
+    (UnitType::BabyDragon, "bd", &["baby"]),
+    (UnitType::BattleSled, "bs", &["sled"]),
+    (UnitType::Catapult, "ca", &["cat", "cata"]),
+    (UnitType::Centipede, "ce", &["cent"]),
+    (UnitType::Cloak, "cl", &[]),
+    (UnitType::Crab, "cr", &[]),
+    (UnitType::Dagger, "da", &[]),
+    (UnitType::DefaultWarrior, "dw", &[]),
+    (UnitType::Defender, "de", &["def"]),
+    (UnitType::Doomux, "do", &[]),
+    (UnitType::Egg, "eg", &[]),
+    (UnitType::Exida, "ex", &[]),
+    (UnitType::FireDragon, "fd", &["dr", "dragon"]),
+    (UnitType::Gaami, "ga", &[]),
+    (UnitType::Giant, "gi", &[]),
+    (UnitType::Hexapod, "hx", &["hexa"]),
+    (UnitType::IceArcher, "ia", &[]),
+    (UnitType::IceFortress, "if", &["fo", "fortress"]),
+    (UnitType::Jelly, "je", &[]),
+    (UnitType::Juggernaut, "ju", &["jug", "jugg"]),
+    (UnitType::Kiton, "ki", &[]),
+    (UnitType::Knight, "kn", &[]),
+    (UnitType::MindBender, "mb", &["mind"]),
+    (UnitType::Mooni, "mo", &[]),
+    (UnitType::Phychi, "ph", &[]),
+    (UnitType::Pirate, "pi", &[]),
+    (UnitType::Polytaur, "po", &["poly"]),
+    (UnitType::Puffer, "pu", &[]),
+    (UnitType::Raychi, "ra", &[]),
+    (UnitType::Rider, "ri", &[]),
+    (UnitType::Segment, "se", &["seg"]),
+    (UnitType::Shaman, "sh", &[]),
+    (UnitType::Shark, "sk", &[]),
+    (UnitType::Swordsman, "sw", &["swords"]),
+    (UnitType::Tridention, "tr", &["tri"]),
+    (UnitType::Warrior, "wa", &["warr"]),
+];
+
+/// Suffix of the naval variants' names and their short codes.
+const NAVAL_NAMES: &[(&str, &str)] = &[
+    ("Bomber", "bo"),
+    ("Raft", "rf"),
+    ("Rammer", "rm"),
+    ("Scout", "sc"),
+];
+
+/// The error returned when a string does not name a [`UnitType`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseUnitTypeError {
+    input: String,
+}
+
+impl fmt::Display for ParseUnitTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown unit type `{}`", self.input)
+    }
+}
+
+impl std::error::Error for ParseUnitTypeError {}
+
+impl UnitType {
+    /// Splits a naval variant into its carried unit and the index of its kind in
+    /// [`NAVAL_NAMES`].
+    pub(super) fn naval_parts(self) -> Option<(UnitType, usize)> {
+        let name: &'static str = self.into();
+
+        NAVAL_NAMES
+            .iter()
+            .enumerate()
+            .find_map(|(kind, (suffix, _))| {
+                let base = find_variant(name.strip_suffix(suffix)?)?;
+                Some((base, kind))
+            })
+    }
+
+    fn short_code(self) -> &'static str {
+        BASE_NAMES
+            .iter()
+            .find(|(unit_type, _, _)| *unit_type == self)
+            .map_or_else(|| self.into(), |(_, code, _)| *code)
+    }
+}
+
+/// Finds the variant whose name is `name`, ignoring ASCII case.
+fn find_variant(name: &str) -> Option<UnitType> {
+    UnitType::VARIANTS.iter().copied().find(|unit_type| {
+        let variant: &'static str = unit_type.into();
+        variant.eq_ignore_ascii_case(name)
+    })
+}
+
+/// Finds a non-naval unit by its long name (with the spaces removed) or one of its aliases.
+fn find_base(name: &str) -> Option<UnitType> {
+    BASE_NAMES
+        .iter()
+        .find(|(unit_type, code, aliases)| {
+            let variant: &'static str = unit_type.into();
+            variant.eq_ignore_ascii_case(name)
+                || code.eq_ignore_ascii_case(name)
+                || aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+        })
+        .map(|(unit_type, _, _)| *unit_type)
+}
+
+fn find_naval_kind(word: &str) -> Option<&'static str> {
+    NAVAL_NAMES
+        .iter()
+        .find(|(suffix, code)| suffix.eq_ignore_ascii_case(word) || code.eq_ignore_ascii_case(word))
+        .map(|(suffix, _)| *suffix)
+}
+
+impl FromStr for UnitType {
+    type Err = ParseUnitTypeError;
+
+    /// Parses a unit from its name or a community alias, ignoring case.
+    ///
+    /// Naval variants are written as the carried unit plus the naval kind on either side,
+    /// e.g. `"bomber wa"`, `"wa bo"`, `"Ice Archer Raft"` or `"WarriorRammer"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseUnitTypeError {
+            input: s.to_owned(),
+        };
+
+        let words: Vec<&str> = s
+            .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        let (first, rest) = words.split_first().ok_or_else(err)?;
+        let (last, init) = words.split_last().ok_or_else(err)?;
+
+        let (kind, base_words) = match (rest.is_empty(), find_naval_kind(first)) {
+            (false, Some(kind)) => (Some(kind), rest),
+            _ => match (rest.is_empty(), find_naval_kind(last)) {
+                (false, Some(kind)) => (Some(kind), init),
+                _ => (None, words.as_slice()),
+            },
+        };
+
+        let base_name = base_words.concat();
+
+        match kind {
+            Some(kind) => {
+                let base: &'static str = find_base(&base_name).ok_or_else(err)?.into();
+                find_variant(&format!("{base}{kind}")).ok_or_else(err)
+            }
+            None => find_base(&base_name)
+                .or_else(|| find_variant(&base_name))
+                .ok_or_else(err),
+        }
+    }
+}
+
+impl fmt::Display for UnitType {
+    /// Writes the unit's long name, e.g. `Ice Archer` or `Warrior Bomber`.
+    ///
+    /// The alternate flag (`{:#}`) writes the short code instead, e.g. `ia` or `wa bo`.
+    /// Both forms parse back with [`UnitType::from_str`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (base, kind) = match self.naval_parts() {
+            Some((base, kind)) => (base, Some(NAVAL_NAMES[kind])),
+            None => (*self, None),
+        };
+
+        if f.alternate() {
+            f.write_str(base.short_code())?;
+            if let Some((_, code)) = kind {
+                write!(f, " {code}")?;
+            }
+        } else {
+            let name: &'static str = base.into();
+            for (i, c) in name.char_indices() {
+                if i > 0 && c.is_ascii_uppercase() {
+                    f.write_str(" ")?;
+                }
+                write!(f, "{c}")?;
+            }
+            if let Some((suffix, _)) = kind {
+                write!(f, " {suffix}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aliases() {
+        for input in ["ri", "rider", "Rider", "RIDER", " ri "] {
+            assert_eq!(input.parse(), Ok(UnitType::Rider));
+        }
+        assert_eq!("kn".parse(), Ok(UnitType::Knight));
+        assert_eq!("ice archer".parse(), Ok(UnitType::IceArcher));
+        assert_eq!("MindBender".parse(), Ok(UnitType::MindBender));
+        assert_eq!("dragon".parse(), Ok(UnitType::FireDragon));
+    }
+
+    #[test]
+    fn test_parse_naval() {
+        for input in [
+            "bomber wa",
+            "wa bomber",
+            "wa bo",
+            "Warrior Bomber",
+            "WarriorBomber",
+        ] {
+            assert_eq!(input.parse(), Ok(UnitType::WarriorBomber));
+        }
+        assert_eq!("rf dw".parse(), Ok(UnitType::DefaultWarriorRaft));
+        assert_eq!("ice archer-rammer".parse(), Ok(UnitType::IceArcherRammer));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!("".parse::<UnitType>().is_err());
+        assert!("wizard".parse::<UnitType>().is_err());
+        assert!("giant bomber".parse::<UnitType>().is_err());
+        assert!("bo".parse::<UnitType>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        assert_eq!(UnitType::IceArcher.to_string(), "Ice Archer");
+        assert_eq!(format!("{:#}", UnitType::WarriorBomber), "wa bo");
+
+        for &unit_type in UnitType::VARIANTS {
+            assert_eq!(unit_type.to_string().parse(), Ok(unit_type));
+            assert_eq!(format!("{unit_type:#}").parse(), Ok(unit_type));
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{
+    Hp, MAX_UNITS, ParseUnitTypeError, StatusEffects, StatusEffectsError, Unit, UnitType, Units,
+};
+
+/// The error returned when parsing a unit, a side or a battle from chat shorthand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseBattleError {
+    /// A unit was left blank.
+    Empty,
+    /// No leading run of words names a unit type.
+    UnitType(ParseUnitTypeError),
+    /// The words after the unit type are not status effects.
+    StatusEffects(StatusEffectsError),
+    /// More than one hp was given, or one above the unit's max hp.
+    Hp(String),
+    /// A side has more than [`MAX_UNITS`] units.
+    TooManyUnits(usize),
+    /// A battle does not have exactly two sides separated by `/`.
+    Sides,
+}
+
+impl fmt::Display for ParseBattleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty unit"),
+            Self::UnitType(err) => write!(f, "{err}"),
+            Self::StatusEffects(err) => write!(f, "{err}"),
+            Self::Hp(unit) => write!(f, "invalid hp in `{unit}`"),
+            Self::TooManyUnits(count) => {
+                write!(
+                    f,
+                    "{count} units on one side, at most {MAX_UNITS} are allowed"
+                )
+            }
+            Self::Sides => write!(f, "expected attackers and defenders separated by `/`"),
+        }
+    }
+}
+
+impl std::error::Error for ParseBattleError {}
+
+impl FromStr for Unit {
+    type Err = ParseBattleError;
+
+    /// Parses a unit type followed by its status effects, with an optional hp anywhere, e.g.
+    /// `wa vd 7` for a veteran, fortified warrior on 7 hp. Without an hp the unit is at its
+    /// max hp.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hps, words): (Vec<&str>, Vec<&str>) = s
+            .split_whitespace()
+            .partition(|word| word.chars().all(|c| c.is_ascii_digit()));
+        if words.is_empty() {
+            return Err(ParseBattleError::Empty);
+        }
+        let hp: Option<Hp> = match hps.as_slice() {
+            [] => None,
+            [hp] => Some(hp.parse().map_err(|_| ParseBattleError::Hp(s.to_owned()))?),
+            _ => return Err(ParseBattleError::Hp(s.to_owned())),
+        };
+
+        // Take the longest run of words that names a unit, so `wa bo` is a bomber rather
+        // than a boosted warrior.
+        let mut last_err = None;
+        for split in (1..=words.len()).rev() {
+            let unit_type = match words[..split].join(" ").parse::<UnitType>() {
+                Ok(unit_type) => unit_type,
+                Err(err) => {
+                    last_err.get_or_insert(ParseBattleError::UnitType(err));
+                    continue;
+                }
+            };
+            let status_effects = words[split..]
+                .join(" ")
+                .parse::<StatusEffects>()
+                .map_err(ParseBattleError::StatusEffects)?;
+
+            let mut unit = Unit::new(unit_type).with_status_effects(status_effects);
+            if let Some(hp) = hp {
+                if hp == 0 || hp > unit.max_hp {
+                    return Err(ParseBattleError::Hp(s.to_owned()));
+                }
+                unit = unit.with_current_hp(hp);
+            }
+            return Ok(unit);
+        }
+
+        Err(last_err.unwrap_or(ParseBattleError::Empty))
+    }
+}
+
+impl FromStr for Units {
+    type Err = ParseBattleError;
+
+    /// Parses units separated by commas, e.g. `wa, ca v, kn 7`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').collect();
+        if parts.len() > MAX_UNITS {
+            return Err(ParseBattleError::TooManyUnits(parts.len()));
+        }
+
+        parts.into_iter().map(str::parse).collect()
+    }
+}
+
+/// Parses attackers and defenders separated by `/`, e.g. `wa, ca v / gi 30, de d`.
+pub fn parse_battle(s: &str) -> Result<(Units, Units), ParseBattleError> {
+    let (attackers, defenders) = s.split_once('/').ok_or(ParseBattleError::Sides)?;
+    if defenders.contains('/') {
+        return Err(ParseBattleError::Sides);
+    }
+
+    Ok((attackers.parse()?, defenders.parse()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unit() {
+        let unit: Unit = "wa vd 7".parse().unwrap();
+        assert_eq!(unit.unit_type, UnitType::Warrior);
+        assert_eq!(unit.current_hp, 7);
+        assert!(
+            unit.status_effects
+                .contains(StatusEffects::VETERAN | StatusEffects::FORTIFIED)
+        );
+
+        let bomber: Unit = "wa bo".parse().unwrap();
+        assert_eq!(bomber.unit_type, UnitType::WarriorBomber);
+
+        assert!(matches!(
+            "wa 40".parse::<Unit>(),
+            Err(ParseBattleError::Hp(_))
+        ));
+        assert!(matches!(
+            "zz".parse::<Unit>(),
+            Err(ParseBattleError::UnitType(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_battle() {
+        let (attackers, defenders) = parse_battle("wa, ca v / gi 30, de d").unwrap();
+        assert_eq!(attackers.len(), 2);
+        assert_eq!(defenders[0].current_hp, 30);
+        assert!(
+            defenders[1]
+                .status_effects
+                .contains(StatusEffects::FORTIFIED)
+        );
+
+        let crowd = vec!["wa"; MAX_UNITS + 1].join(",");
+        assert_eq!(
+            parse_battle(&format!("{crowd} / wa")).unwrap_err(),
+            ParseBattleError::TooManyUnits(MAX_UNITS + 1)
+        );
+        assert_eq!(parse_battle("wa").unwrap_err(), ParseBattleError::Sides);
+    }
+}
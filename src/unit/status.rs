@@ -0,0 +1,174 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::StatusEffects;
+
+/// One-letter shorthand used in chat, e.g. `wa vd` for a veteran, fortified warrior.
+const SHORTHANDS: &[(StatusEffects, char)] = &[
+    (StatusEffects::VETERAN, 'v'),
+    (StatusEffects::FORTIFIED, 'd'),
+    (StatusEffects::WALLED, 'w'),
+    (StatusEffects::POISONED, 'p'),
+    (StatusEffects::BOOSTED, 'b'),
+    (StatusEffects::FROZEN, 'f'),
+    (StatusEffects::CONVERTED, 'c'),
+];
+
+/// Pairs of status effects that a unit can never have at the same time.
+const CONFLICTS: &[(StatusEffects, StatusEffects)] =
+    &[(StatusEffects::FORTIFIED, StatusEffects::WALLED)];
+
+/// The error returned when parsing or validating [`StatusEffects`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusEffectsError {
+    /// The word is neither a status effect name nor a string of shorthand letters.
+    Unknown(String),
+    /// The two status effects cannot be applied to the same unit.
+    Conflict(StatusEffects, StatusEffects),
+}
+
+impl fmt::Display for StatusEffectsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(word) => write!(f, "unknown status effect `{word}`"),
+            Self::Conflict(a, b) => write!(f, "a unit cannot be both {a} and {b}"),
+        }
+    }
+}
+
+impl std::error::Error for StatusEffectsError {}
+
+impl StatusEffects {
+    /// Checks that no two of the status effects are mutually exclusive.
+    pub fn validate(self) -> Result<(), StatusEffectsError> {
+        match CONFLICTS
+            .iter()
+            .find(|(a, b)| self.contains(*a) && self.contains(*b))
+        {
+            Some((a, b)) => Err(StatusEffectsError::Conflict(*a, *b)),
+            None => Ok(()),
+        }
+    }
+
+    fn from_shorthand(letter: char) -> Option<Self> {
+        SHORTHANDS
+            .iter()
+            .find(|(_, c)| c.eq_ignore_ascii_case(&letter))
+            .map(|(flag, _)| *flag)
+    }
+}
+
+impl FromStr for StatusEffects {
+    type Err = StatusEffectsError;
+
+    /// Parses status effects separated by spaces, commas or `|`.
+    ///
+    /// Each word is either a full name, ignoring case (`fortified`), or a run of shorthand
+    /// letters (`vd`). The result is checked with [`StatusEffects::validate`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut out = StatusEffects::empty();
+
+        for word in s
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '|')
+            .filter(|word| !word.is_empty())
+        {
+            if let Some((_, flag)) = StatusEffects::all()
+                .iter_names()
+                .find(|(name, _)| name.eq_ignore_ascii_case(word))
+            {
+                out.insert(flag);
+                continue;
+            }
+
+            for letter in word.chars() {
+                let flag = StatusEffects::from_shorthand(letter)
+                    .ok_or_else(|| StatusEffectsError::Unknown(word.to_owned()))?;
+                out.insert(flag);
+            }
+        }
+
+        out.validate()?;
+
+        Ok(out)
+    }
+}
+
+impl fmt::Display for StatusEffects {
+    /// Writes the full names separated by commas, e.g. `VETERAN, FORTIFIED`.
+    ///
+    /// The alternate flag (`{:#}`) writes the shorthand letters instead, followed by the full
+    /// names of any status effects without a shorthand, e.g. `vd SPLASHING`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            for (i, (name, _)) in self.iter_names().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                f.write_str(name)?;
+            }
+            return Ok(());
+        }
+
+        let mut rest = *self;
+        for (flag, letter) in SHORTHANDS {
+            if self.contains(*flag) {
+                write!(f, "{letter}")?;
+                rest.remove(*flag);
+            }
+        }
+
+        let mut separate = rest != *self;
+        for (name, _) in rest.iter_names() {
+            if separate {
+                f.write_str(" ")?;
+            }
+            f.write_str(name)?;
+            separate = true;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shorthand_and_names() {
+        assert_eq!(
+            "vd".parse(),
+            Ok(StatusEffects::VETERAN | StatusEffects::FORTIFIED)
+        );
+        assert_eq!(
+            "v, Poisoned | splashing".parse(),
+            Ok(StatusEffects::VETERAN | StatusEffects::POISONED | StatusEffects::SPLASHING)
+        );
+        assert_eq!("".parse(), Ok(StatusEffects::empty()));
+        assert_eq!(
+            "vx".parse::<StatusEffects>(),
+            Err(StatusEffectsError::Unknown("vx".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_parse_conflict() {
+        assert_eq!(
+            "dw".parse::<StatusEffects>(),
+            Err(StatusEffectsError::Conflict(
+                StatusEffects::FORTIFIED,
+                StatusEffects::WALLED
+            ))
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let effects = StatusEffects::VETERAN | StatusEffects::BOOSTED | StatusEffects::EXPLODING;
+
+        assert_eq!(effects.to_string(), "BOOSTED, EXPLODING, VETERAN");
+        assert_eq!(format!("{effects:#}"), "vb EXPLODING");
+        assert_eq!(effects.to_string().parse(), Ok(effects));
+        assert_eq!(format!("{effects:#}").parse(), Ok(effects));
+    }
+}
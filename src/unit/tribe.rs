@@ -0,0 +1,82 @@
+use std::fmt;
+use std::str::FromStr;
+
+use strum::{IntoStaticStr, VariantArray};
+
+use super::UnitType;
+
+/// The tribe that can train a unit.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, IntoStaticStr, VariantArray)]
+pub enum Tribe {
+    /// Units every tribe can train.
+    Common,
+    Aquarion,
+    Cymanti,
+    Elyrion,
+    Polaris,
+}
+
+impl Tribe {
+    /// Whether the tribe can field units of `other`. Every tribe can field common units.
+    pub fn fields(self, other: Tribe) -> bool {
+        other == Tribe::Common || other == self
+    }
+}
+
+/// The error returned when a string is not the name of a [`Tribe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTribeError {
+    input: String,
+}
+
+impl fmt::Display for ParseTribeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown tribe `{}`", self.input)
+    }
+}
+
+impl std::error::Error for ParseTribeError {}
+
+impl FromStr for Tribe {
+    type Err = ParseTribeError;
+
+    /// Parses a tribe from its name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Tribe::VARIANTS
+            .iter()
+            .copied()
+            .find(|tribe| <&str>::from(*tribe).eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParseTribeError {
+                input: s.to_owned(),
+            })
+    }
+}
+
+impl fmt::Display for Tribe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.into())
+    }
+}
+
+impl UnitType {
+    /// The tribe that trains the unit. Naval variants belong to the tribe of the unit they
+    /// carry.
+    pub fn tribe(self) -> Tribe {
+        self.metadata().tribe
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tribes() {
+        assert_eq!(UnitType::Warrior.tribe(), Tribe::Common);
+        assert_eq!(UnitType::Mooni.tribe(), Tribe::Polaris);
+        assert_eq!(UnitType::KitonRammer.tribe(), Tribe::Cymanti);
+        assert_eq!("elyrion".parse(), Ok(Tribe::Elyrion));
+        assert!(Tribe::Aquarion.fields(Tribe::Common));
+        assert!(!Tribe::Aquarion.fields(Tribe::Polaris));
+    }
+}
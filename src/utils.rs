@@ -30,7 +30,7 @@ impl Iterator for Perms {
 
         while self.i < self.len {
             if self.c[self.i] < self.i {
-                if self.i % 2 == 0 {
+                if self.i.is_multiple_of(2) {
                     pair = (0, self.i);
                 } else {
                     pair = (self.c[self.i], self.i);